@@ -0,0 +1,454 @@
+use crate::{
+    batch::{BatchItem, BatchResult},
+    format::ResponseFormat,
+    weather_forecast::Forecast,
+    weather_report::WeatherReport,
+    DwdError,
+};
+use arrow_array::{
+    builder::{Float64Builder, StringBuilder, TimestampMillisecondBuilder},
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono_tz::Tz;
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+pub const ARROW_STREAM: &str = "application/vnd.apache.arrow.stream";
+pub const PARQUET: &str = "application/vnd.apache.parquet";
+
+/// The data of one station as columns, before it's converted to Arrow.
+pub struct Table {
+    /// The name of the timestamp column (`time_steps` or `timestamp`)
+    time_column: &'static str,
+    /// Epoch milliseconds
+    times: Vec<Option<i64>>,
+    columns: Vec<TableColumn>,
+}
+
+struct TableColumn {
+    name: String,
+    unit: Option<String>,
+    values: ColumnValues,
+}
+
+enum ColumnValues {
+    Float(Vec<Option<f64>>),
+    Text(Vec<Option<String>>),
+}
+
+impl Table {
+    /// `time_steps` first, then the elements sorted by key and unknown elements (`?raw=true`).
+    /// The time steps have to be epoch milliseconds.
+    pub fn from_forecast(forecast: &Forecast) -> Self {
+        let floats =
+            |values: &Vec<Value>| ColumnValues::Float(values.iter().map(Value::as_f64).collect());
+        let mut keys: Vec<&&str> = forecast
+            .data
+            .keys()
+            .filter(|&&key| key != "time_steps")
+            .collect();
+        keys.sort();
+        let mut columns: Vec<TableColumn> = keys
+            .into_iter()
+            .map(|key| TableColumn {
+                name: key.to_string(),
+                unit: forecast.units.get(*key).map(|u| u.symbol().to_owned()),
+                values: floats(&forecast.data[*key]),
+            })
+            .collect();
+        if let Some(raw) = &forecast.raw {
+            let mut names: Vec<&String> = raw.keys().collect();
+            names.sort();
+            columns.extend(names.into_iter().map(|name| TableColumn {
+                name: name.clone(),
+                unit: None,
+                values: floats(&raw[name]),
+            }));
+        }
+
+        Table {
+            time_column: "time_steps",
+            times: forecast
+                .data
+                .get("time_steps")
+                .map(|times| times.iter().map(Value::as_i64).collect())
+                .unwrap_or_default(),
+            columns,
+        }
+    }
+
+    /// `timestamp` first, then the parameters and unknown columns (`extra`) as text.
+    pub fn from_report(report: &WeatherReport) -> Self {
+        let params: BTreeSet<_> = report
+            .data
+            .iter()
+            .flat_map(|record| record.values.keys().copied())
+            .chain(report.units.keys().copied())
+            .collect();
+        let extra: BTreeSet<&String> = report
+            .data
+            .iter()
+            .flat_map(|record| record.extra.keys())
+            .collect();
+
+        let columns = params
+            .into_iter()
+            .map(|param| TableColumn {
+                name: param.key().to_owned(),
+                unit: report.units.get(&param).map(|u| u.symbol().to_owned()),
+                values: ColumnValues::Float(
+                    report
+                        .data
+                        .iter()
+                        .map(|record| record.values.get(&param).copied())
+                        .collect(),
+                ),
+            })
+            .chain(extra.into_iter().map(|name| {
+                TableColumn {
+                    name: name.clone(),
+                    unit: report.extra_units.get(name).cloned(),
+                    values: ColumnValues::Text(
+                        report
+                            .data
+                            .iter()
+                            .map(|record| match record.extra.get(name)? {
+                                Value::Null => None,
+                                Value::String(s) => Some(s.clone()),
+                                other => Some(other.to_string()),
+                            })
+                            .collect(),
+                    ),
+                }
+            }))
+            .collect();
+
+        Table {
+            time_column: "timestamp",
+            times: report
+                .data
+                .iter()
+                .map(|record| Some(record.timestamp.millis()))
+                .collect(),
+            columns,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.times.len()
+    }
+}
+
+/// One table per station. Columns are matched by name, missing values are null.
+/// With `station_column`, a `station` column is added in front.
+fn to_record_batch(
+    tables: &[(String, Table)],
+    station_column: bool,
+    tz: Option<Tz>,
+    metadata: HashMap<String, String>,
+) -> Result<RecordBatch, DwdError> {
+    let Some((_, first)) = tables.first() else {
+        let schema = Schema::empty().with_metadata(metadata);
+        return Ok(RecordBatch::new_empty(Arc::new(schema)));
+    };
+    let mut fields = Vec::new();
+    let mut arrays: Vec<ArrayRef> = Vec::new();
+
+    if station_column {
+        let mut builder = StringBuilder::new();
+        for (station, table) in tables {
+            (0..table.len()).for_each(|_| builder.append_value(station));
+        }
+        fields.push(Field::new("station", DataType::Utf8, false));
+        arrays.push(Arc::new(builder.finish()));
+    }
+
+    let tz: Arc<str> = Arc::from(tz.unwrap_or(Tz::UTC).name());
+    let mut builder = TimestampMillisecondBuilder::new().with_timezone(tz.clone());
+    for (_, table) in tables {
+        builder.extend(table.times.iter().copied());
+    }
+    fields.push(Field::new(
+        first.time_column,
+        DataType::Timestamp(TimeUnit::Millisecond, Some(tz)),
+        true,
+    ));
+    arrays.push(Arc::new(builder.finish()));
+
+    // in the order they first appear
+    let mut names: Vec<&str> = Vec::new();
+    for (_, table) in tables {
+        for column in &table.columns {
+            if !names.contains(&column.name.as_str()) {
+                names.push(&column.name);
+            }
+        }
+    }
+    for name in names {
+        let columns: Vec<(usize, Option<&TableColumn>)> = tables
+            .iter()
+            .map(|(_, table)| {
+                let column = table.columns.iter().find(|c| c.name == name);
+                (table.len(), column)
+            })
+            .collect();
+        let unit = columns.iter().find_map(|&(_, column)| column?.unit.clone());
+        let is_text = columns.iter().any(
+            |(_, column)| matches!(column, Some(c) if matches!(c.values, ColumnValues::Text(_))),
+        );
+
+        let (data_type, array): (DataType, ArrayRef) = if is_text {
+            let mut builder = StringBuilder::new();
+            for (len, column) in columns {
+                match column.map(|c| &c.values) {
+                    Some(ColumnValues::Text(values)) => builder.extend(values.iter().cloned()),
+                    Some(ColumnValues::Float(values)) => {
+                        builder.extend(values.iter().map(|v| v.map(|v| v.to_string())))
+                    }
+                    None => (0..len).for_each(|_| builder.append_null()),
+                }
+            }
+            (DataType::Utf8, Arc::new(builder.finish()))
+        } else {
+            let mut builder = Float64Builder::new();
+            for (len, column) in columns {
+                match column.map(|c| &c.values) {
+                    Some(ColumnValues::Float(values)) => builder.extend(values.iter().copied()),
+                    _ => builder.append_nulls(len),
+                }
+            }
+            (DataType::Float64, Arc::new(builder.finish()))
+        };
+        let field = Field::new(name, data_type, true);
+        fields.push(match unit {
+            Some(unit) => field.with_metadata(HashMap::from([("unit".to_owned(), unit)])),
+            None => field,
+        });
+        arrays.push(array);
+    }
+
+    let schema = Schema::new(fields).with_metadata(metadata);
+    RecordBatch::try_new(Arc::new(schema), arrays).map_err(|_| DwdError::InternalError)
+}
+
+/// A single station.
+pub fn table_to_record_batch(table: Table, tz: Option<Tz>) -> Result<RecordBatch, DwdError> {
+    to_record_batch(&[(String::new(), table)], false, tz, HashMap::new())
+}
+
+/// All stations of a batch request in one table with a `station` column.
+/// Failed stations are left out and listed in the schema's `errors` metadata (as JSON).
+pub fn batch_to_record_batch<T>(
+    items: Vec<BatchItem<T>>,
+    to_table: impl Fn(&T) -> Table,
+    tz: Option<Tz>,
+) -> Result<RecordBatch, DwdError> {
+    let mut tables = Vec::new();
+    let mut errors = Vec::new();
+    for item in items {
+        match item.result {
+            BatchResult::Data(data) => tables.push((item.station, to_table(&data))),
+            BatchResult::Error(error) => errors.push(serde_json::json!({
+                "station": item.station,
+                "status": error.status,
+                "message": error.message,
+            })),
+        }
+    }
+    let metadata = HashMap::from([("errors".to_owned(), Value::Array(errors).to_string())]);
+    to_record_batch(&tables, true, tz, metadata)
+}
+
+/// Encodes the batch as an Arrow IPC stream or a Parquet file.
+pub fn encode_record_batch(
+    batch: &RecordBatch,
+    format: ResponseFormat,
+) -> Result<Vec<u8>, DwdError> {
+    let mut out = Vec::new();
+    match format {
+        ResponseFormat::Arrow => {
+            let mut writer = arrow_ipc::writer::StreamWriter::try_new(&mut out, &batch.schema())
+                .map_err(|_| DwdError::InternalError)?;
+            writer.write(batch).map_err(|_| DwdError::InternalError)?;
+            writer.finish().map_err(|_| DwdError::InternalError)?;
+        }
+        ResponseFormat::Parquet => {
+            let mut writer = parquet::arrow::ArrowWriter::try_new(&mut out, batch.schema(), None)
+                .map_err(|_| DwdError::InternalError)?;
+            writer.write(batch).map_err(|_| DwdError::InternalError)?;
+            writer.close().map_err(|_| DwdError::InternalError)?;
+        }
+        _ => return Err(DwdError::UnsupportedFormat),
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batch::BatchError, diagnostics::ParseMode, time_format::Timestamp, units::Unit,
+        weather_report::parse_weather_report,
+    };
+    use actix_web::web::Bytes;
+    use arrow_array::{cast::AsArray, types::TimestampMillisecondType, Array};
+    use arrow_ipc::reader::StreamReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::{collections::BTreeMap, io::Cursor};
+
+    const HOUR: i64 = 3_600_000;
+
+    fn forecast() -> Forecast {
+        Forecast {
+            name: "10865".to_owned(),
+            description: String::new(),
+            issuer: String::new(),
+            generating_process: String::new(),
+            issue_time: Timestamp::from_millis(0),
+            reference_models: Vec::new(),
+            coordinates: String::new(),
+            location: None,
+            data: HashMap::from([
+                ("time_steps", [0, HOUR, 2 * HOUR].map(Value::from).to_vec()),
+                (
+                    "temp",
+                    vec![Value::from(288.15), Value::Null, Value::from(289.15)],
+                ),
+                ("wind_speed", [2.0, 3.0, 4.0].map(Value::from).to_vec()),
+            ]),
+            units: BTreeMap::from([
+                ("temp", Unit::Kelvin),
+                ("wind_speed", Unit::MetersPerSecond),
+            ]),
+            n_data_points: 3,
+            weather: None,
+            raw: Some(HashMap::from([(
+                "XYZ".to_owned(),
+                [1.0, 2.0, 3.0].map(Value::from).to_vec(),
+            )])),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn report() -> WeatherReport {
+        let csv = "surface observations;;dry_bulb_temperature_at_2_meter_above_ground;remark
+Parameter description;;Grad C;-
+Datum;Uhrzeit (UTC);Temperatur;Bemerkung
+24.10.26;21:00;12,3;light
+24.10.26;20:00;---;
+";
+        parse_weather_report(csv.as_bytes(), ParseMode::Strict).unwrap()
+    }
+
+    fn read_ipc(bytes: Vec<u8>) -> RecordBatch {
+        let mut reader = StreamReader::try_new(Cursor::new(bytes), None).unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert!(reader.next().is_none());
+        batch
+    }
+
+    fn read_parquet(bytes: Vec<u8>) -> RecordBatch {
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(bytes))
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert!(reader.next().is_none());
+        batch
+    }
+
+    fn names(batch: &RecordBatch) -> Vec<String> {
+        let schema = batch.schema();
+        schema.fields().iter().map(|f| f.name().clone()).collect()
+    }
+
+    fn unit(batch: &RecordBatch, name: &str) -> Option<String> {
+        let schema = batch.schema();
+        let field = schema.field_with_name(name).unwrap();
+        field.metadata().get("unit").cloned()
+    }
+
+    fn nulls(batch: &RecordBatch, name: &str) -> Vec<bool> {
+        let column = batch.column_by_name(name).unwrap();
+        (0..column.len()).map(|i| column.is_null(i)).collect()
+    }
+
+    #[test]
+    fn forecast_as_arrow_stream() {
+        let batch =
+            table_to_record_batch(Table::from_forecast(&forecast()), Some(chrono_tz::UTC)).unwrap();
+        let batch = read_ipc(encode_record_batch(&batch, ResponseFormat::Arrow).unwrap());
+
+        assert_eq!(names(&batch), ["time_steps", "temp", "wind_speed", "XYZ"]);
+        assert_eq!(
+            batch.schema().field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+        );
+        let times = batch.column(0).as_primitive::<TimestampMillisecondType>();
+        assert_eq!(times.values(), &[0, HOUR, 2 * HOUR]);
+        assert_eq!(nulls(&batch, "temp"), [false, true, false]);
+        assert_eq!(batch.column(1).data_type(), &DataType::Float64);
+        assert_eq!(unit(&batch, "temp").as_deref(), Some("K"));
+        assert_eq!(unit(&batch, "wind_speed").as_deref(), Some("m/s"));
+        // unknown elements don't have a unit
+        assert_eq!(unit(&batch, "XYZ"), None);
+    }
+
+    #[test]
+    fn report_as_parquet() {
+        let tz = Some(chrono_tz::Europe::Berlin);
+        let batch = table_to_record_batch(Table::from_report(&report()), tz).unwrap();
+        let batch = read_parquet(encode_record_batch(&batch, ResponseFormat::Parquet).unwrap());
+
+        assert_eq!(names(&batch), ["timestamp", "temp", "remark"]);
+        assert_eq!(
+            batch.schema().field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Millisecond, Some("Europe/Berlin".into()))
+        );
+        assert_eq!(nulls(&batch, "temp"), [false, true]);
+        assert_eq!(unit(&batch, "temp").as_deref(), Some("°C"));
+        let remark = batch.column_by_name("remark").unwrap().as_string::<i32>();
+        assert_eq!(remark.value(0), "light");
+        assert!(remark.is_null(1));
+    }
+
+    #[test]
+    fn batch_errors_in_the_schema_metadata() {
+        let items = vec![
+            BatchItem {
+                station: "10865".to_owned(),
+                result: BatchResult::Data(forecast()),
+            },
+            BatchItem {
+                station: "XXXXX".to_owned(),
+                result: BatchResult::Error(BatchError {
+                    status: 404,
+                    message: "No forecast was found for this station".to_owned(),
+                }),
+            },
+        ];
+        let batch = batch_to_record_batch(items, Table::from_forecast, None).unwrap();
+        let batch = read_ipc(encode_record_batch(&batch, ResponseFormat::Arrow).unwrap());
+
+        assert_eq!(
+            names(&batch),
+            ["station", "time_steps", "temp", "wind_speed", "XYZ"]
+        );
+        let stations = batch.column(0).as_string::<i32>();
+        assert!(stations.iter().all(|station| station == Some("10865")));
+        assert_eq!(batch.num_rows(), 3);
+        let errors: Value = serde_json::from_str(&batch.schema().metadata()["errors"]).unwrap();
+        assert_eq!(
+            errors,
+            serde_json::json!([{
+                "station": "XXXXX",
+                "status": 404,
+                "message": "No forecast was found for this station"
+            }])
+        );
+    }
+}
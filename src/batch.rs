@@ -0,0 +1,131 @@
+use crate::DwdError;
+use actix_web::ResponseError;
+use futures::{stream, Future, Stream, StreamExt};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The maximum number of stations per batch request.
+pub const MAX_BATCH_SIZE: usize = 100;
+/// How many stations are requested from the DWD at the same time.
+const CONCURRENCY: usize = 8;
+
+#[derive(Deserialize, ToSchema)]
+pub struct BatchRequest {
+    pub stations: Vec<String>,
+}
+
+impl BatchRequest {
+    /// The stations without duplicates (in the order they were first requested).
+    pub fn stations(self) -> Result<Vec<String>, DwdError> {
+        let stations: Vec<String> = self.stations.into_iter().unique().collect();
+        if stations.len() > MAX_BATCH_SIZE {
+            return Err(DwdError::BatchTooLarge(MAX_BATCH_SIZE));
+        }
+        Ok(stations)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchItem<T> {
+    pub station: String,
+    #[serde(flatten)]
+    pub result: BatchResult<T>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchResult<T> {
+    Data(T),
+    Error(BatchError),
+}
+
+/// The error response of the single-station endpoint.
+#[derive(Serialize, ToSchema)]
+pub struct BatchError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl<T> From<Result<T, DwdError>> for BatchResult<T> {
+    fn from(result: Result<T, DwdError>) -> Self {
+        match result {
+            Ok(data) => BatchResult::Data(data),
+            Err(e) => BatchResult::Error(BatchError {
+                status: e.status_code().as_u16(),
+                message: e.to_string(),
+            }),
+        }
+    }
+}
+
+fn batch_items<T, F, Fut>(
+    stations: Vec<String>,
+    f: F,
+) -> impl Stream<Item = impl Future<Output = BatchItem<T>>>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, DwdError>>,
+{
+    stream::iter(stations).map(move |station| {
+        let result = f(station.clone());
+        async move {
+            BatchItem {
+                station,
+                result: result.await.into(),
+            }
+        }
+    })
+}
+
+/// Runs `f` for every station with a bounded concurrency. The results are in the order of `stations`.
+pub async fn run_batch<T, F, Fut>(stations: Vec<String>, f: F) -> Vec<BatchItem<T>>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, DwdError>>,
+{
+    batch_items(stations, f)
+        .buffered(CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Like [`run_batch`], but yields each result as soon as it's ready (not in the order of `stations`).
+pub fn stream_batch<T, F, Fut>(stations: Vec<String>, f: F) -> impl Stream<Item = BatchItem<T>>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, DwdError>>,
+{
+    batch_items(stations, f).buffer_unordered(CONCURRENCY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(stations: &[&str]) -> BatchRequest {
+        BatchRequest {
+            stations: stations.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn duplicates_are_removed_in_order() {
+        let stations = request(&["10865", "10384", "10865", "P0489", "10384"]).stations();
+        assert_eq!(stations.unwrap(), ["10865", "10384", "P0489"]);
+    }
+
+    #[test]
+    fn duplicates_dont_count_towards_the_limit() {
+        let mut stations = vec!["10865"; MAX_BATCH_SIZE * 2];
+        stations.push("10384");
+        assert_eq!(request(&stations).stations().unwrap().len(), 2);
+
+        let distinct: Vec<String> = (0..=MAX_BATCH_SIZE).map(|i| i.to_string()).collect();
+        let distinct: Vec<&str> = distinct.iter().map(String::as_str).collect();
+        assert!(matches!(
+            request(&distinct).stations(),
+            Err(DwdError::BatchTooLarge(MAX_BATCH_SIZE))
+        ));
+    }
+}
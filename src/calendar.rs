@@ -0,0 +1,257 @@
+use crate::{
+    daily::{DailyForecast, DailySummary},
+    units::{Quantity, Unit, UnitSystem},
+};
+use chrono::{DateTime, NaiveDate};
+
+pub const ICALENDAR: &str = "text/calendar; charset=utf-8";
+
+/// How often calendar apps should refresh the feed (MOSMIX_L is issued every six hours).
+const REFRESH_INTERVAL: &str = "PT6H";
+
+/// One all-day event per day of the daily forecast (RFC 5545).
+/// Values are shown in `system` (metric if it's [`UnitSystem::Raw`]).
+pub fn daily_to_ics(daily: &DailyForecast, description: &str, system: UnitSystem) -> String {
+    let system = match system {
+        UnitSystem::Raw => UnitSystem::Metric,
+        system => system,
+    };
+    let dtstamp = DateTime::from_timestamp_millis(daily.issue_time.millis())
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//dwd-api//Daily forecast//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+        "METHOD:PUBLISH".to_owned(),
+        format!("X-WR-CALNAME:{}", escape(&format!("Weather {description}"))),
+        format!("REFRESH-INTERVAL;VALUE=DURATION:{REFRESH_INTERVAL}"),
+        format!("X-PUBLISHED-TTL:{REFRESH_INTERVAL}"),
+    ];
+    for day in &daily.days {
+        let Ok(date) = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d") else {
+            continue;
+        };
+        let next = date.succ_opt().unwrap_or(date);
+        lines.extend([
+            "BEGIN:VEVENT".to_owned(),
+            // stable per station and day, so a refresh replaces the event
+            format!("UID:{}-{}@dwd-api", daily.name, date.format("%Y%m%d")),
+            format!("DTSTAMP:{dtstamp}"),
+            format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")),
+            format!("SUMMARY:{}", escape(&summary(day, system))),
+            format!("DESCRIPTION:{}", escape(&details(day, system))),
+            "TRANSP:TRANSPARENT".to_owned(),
+            "END:VEVENT".to_owned(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_owned());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+fn convert(
+    value: Option<f64>,
+    from: Unit,
+    system: UnitSystem,
+    quantity: Quantity,
+) -> Option<(f64, Unit)> {
+    let to = system.target(quantity).unwrap_or(from);
+    Some((from.convert_quantity(value?, to, quantity)?, to))
+}
+
+/// E.g. `☀ 18°/7°, 20% rain`. Missing values are left out.
+fn summary(day: &DailySummary, system: UnitSystem) -> String {
+    // rounded as integer, so -0.4 isn't shown as `-0°`
+    let temp = |value| {
+        convert(value, Unit::Kelvin, system, Quantity::Temperature)
+            .map(|(t, _)| format!("{}°", t.round() as i64))
+    };
+    let head: Vec<String> = [
+        day.weather.map(|w| w.category.symbol().to_owned()),
+        match (temp(day.max_temp), temp(day.min_temp)) {
+            (Some(max), Some(min)) => Some(format!("{max}/{min}")),
+            (max, min) => max.or(min),
+        },
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let rain = day
+        .max_precipitation_probability
+        .map(|p| format!("{p:.0}% rain"));
+
+    [Some(head.join(" ")).filter(|h| !h.is_empty()), rain]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn details(day: &DailySummary, system: UnitSystem) -> String {
+    let mut lines: Vec<String> = day
+        .weather
+        .map(|w| w.description_en.to_owned())
+        .into_iter()
+        .collect();
+    let quantities = [
+        (
+            "Precipitation",
+            day.precipitation,
+            Unit::KilogramsPerSquareMeter,
+            Quantity::Precipitation,
+        ),
+        (
+            "Max. wind gust",
+            day.max_wind_gust,
+            Unit::MetersPerSecond,
+            Quantity::Speed,
+        ),
+    ];
+    for (label, value, unit, quantity) in quantities {
+        if let Some((value, unit)) = convert(value, unit, system, quantity) {
+            lines.push(format!("{label}: {value:.1} {}", unit.symbol()));
+        }
+    }
+    if let Some(hours) = day.sunshine_hours {
+        lines.push(format!("Sunshine: {hours:.1} h"));
+    }
+    lines.join("\n")
+}
+
+/// Escapes a TEXT value.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line into lines of at most 75 octets, without splitting UTF-8 characters.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        daily::DEFAULT_TIMEZONE, time_format::Timestamp, weather_code::decode_present_weather,
+    };
+
+    fn day(date: &str, max_temp: Option<f64>, min_temp: Option<f64>) -> DailySummary {
+        DailySummary {
+            date: date.to_owned(),
+            start: Timestamp::from_millis(0),
+            end: Timestamp::from_millis(0),
+            n_time_steps: 24,
+            min_temp,
+            max_temp,
+            precipitation: Some(1.5),
+            max_wind_gust: Some(10.0),
+            sunshine_hours: Some(6.5),
+            significant_weather: Some(0),
+            max_precipitation_probability: Some(20.0),
+            weather: decode_present_weather(0.0),
+        }
+    }
+
+    /// The physical lines of a folded content line.
+    fn segments(folded: &str) -> Vec<&str> {
+        folded.split("\r\n ").collect()
+    }
+
+    #[test]
+    fn fold_at_75_octets() {
+        assert_eq!(fold("SUMMARY:short"), "SUMMARY:short");
+
+        let line = "X".repeat(80);
+        let folded = fold(&line);
+        assert_eq!(segments(&folded), ["X".repeat(75), "X".repeat(5)]);
+        // continuation lines start with a space, which counts towards their length
+        let line = "X".repeat(150);
+        let lengths: Vec<usize> = segments(&fold(&line)).iter().map(|s| s.len()).collect();
+        assert_eq!(lengths, [75, 74, 1]);
+    }
+
+    #[test]
+    fn fold_keeps_multi_byte_characters() {
+        // `°` (2 octets) doesn't fit after 74 octets
+        let line = format!("{}°C", "X".repeat(74));
+        assert_eq!(segments(&fold(&line)), ["X".repeat(74), "°C".to_owned()]);
+
+        let line = format!("SUMMARY:{}", "☀".repeat(40));
+        let folded = fold(&line);
+        assert!(segments(&folded).iter().all(|segment| segment.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn escape_text() {
+        assert_eq!(escape("a;b,c\\d\ne"), r"a\;b\,c\\d\ne");
+        assert_eq!(escape("☀ 18°/7°"), "☀ 18°/7°");
+    }
+
+    #[test]
+    fn summary_format() {
+        let sunny = day("2024-06-10", Some(291.15), Some(280.15));
+        assert_eq!(summary(&sunny, UnitSystem::Metric), "☀ 18°/7°, 20% rain");
+        assert_eq!(summary(&sunny, UnitSystem::Imperial), "☀ 64°/45°, 20% rain");
+
+        // missing values are left out, -0.4° isn't `-0°`
+        let mut partial = day("2024-06-10", Some(272.75), None);
+        partial.weather = None;
+        assert_eq!(summary(&partial, UnitSystem::Metric), "0°, 20% rain");
+        partial.max_temp = None;
+        assert_eq!(summary(&partial, UnitSystem::Metric), "20% rain");
+        partial.max_precipitation_probability = None;
+        assert_eq!(summary(&partial, UnitSystem::Metric), "");
+    }
+
+    #[test]
+    fn calendar_structure() {
+        let daily = DailyForecast {
+            name: "10865".to_owned(),
+            timezone: DEFAULT_TIMEZONE,
+            // 2024-06-10T03:00:00Z
+            issue_time: Timestamp::from_millis(1717988400000),
+            days: vec![
+                day("2024-06-10", Some(291.15), Some(280.15)),
+                day("2024-06-11", Some(293.15), Some(282.15)),
+            ],
+            warnings: Vec::new(),
+        };
+        let ics = daily_to_ics(&daily, "MUENCHEN STADT", UnitSystem::Raw);
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        // every line ends with CRLF
+        assert_eq!(ics.matches('\n').count(), ics.matches("\r\n").count());
+        assert_eq!(ics.matches("BEGIN:VEVENT\r\n").count(), 2);
+        assert_eq!(ics.matches("END:VEVENT\r\n").count(), 2);
+        for (start, end) in [("20240610", "20240611"), ("20240611", "20240612")] {
+            assert!(ics.contains(&format!(
+                "UID:10865-{start}@dwd-api\r\nDTSTAMP:20240610T030000Z\r\nDTSTART;VALUE=DATE:{start}\r\nDTEND;VALUE=DATE:{end}\r\n"
+            )));
+        }
+        assert!(ics.contains("SUMMARY:☀ 18°/7°\\, 20% rain\r\n"));
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains(
+            "DESCRIPTION:Clear sky\\nPrecipitation: 1.5 mm\\nMax. wind gust: 36.0 km/h\\nSunshine: 6.5 h\r\n"
+        ));
+    }
+}
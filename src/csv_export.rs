@@ -0,0 +1,332 @@
+use crate::{units::Unit, weather_forecast::Forecast, weather_report::WeatherReport, DwdError};
+use actix_web::web;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use utoipa::IntoParams;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CsvQuery {
+    /// Defaults to `;` if the decimal separator is `,` and `,` otherwise
+    #[param(value_type = Option<String>)]
+    pub delimiter: Option<char>,
+    /// The decimal separator, defaults to `.`
+    #[serde(default = "default_decimal")]
+    #[param(value_type = Option<String>)]
+    pub decimal: char,
+    /// Prefix the file with a UTF-8 BOM, so Excel detects the encoding
+    #[serde(default)]
+    pub bom: bool,
+}
+
+fn default_decimal() -> char {
+    '.'
+}
+
+struct CsvOptions {
+    delimiter: u8,
+    decimal: char,
+    bom: bool,
+}
+
+impl CsvQuery {
+    /// Only called if CSV was requested, so stray options don't fail requests for other formats.
+    pub fn from_query(query: &str) -> Result<Self, DwdError> {
+        web::Query::<CsvQuery>::from_query(query)
+            .map(web::Query::into_inner)
+            .map_err(|e| DwdError::InvalidCsvOptions(e.to_string()))
+    }
+
+    fn options(&self) -> Result<CsvOptions, DwdError> {
+        let delimiter = self
+            .delimiter
+            .unwrap_or(if self.decimal == ',' { ';' } else { ',' });
+        if !delimiter.is_ascii() {
+            return Err(DwdError::InvalidCsvOptions(
+                "the delimiter has to be ASCII".to_owned(),
+            ));
+        }
+        if ['"', '\r', '\n'].contains(&delimiter) || ['"', '\r', '\n'].contains(&self.decimal) {
+            return Err(DwdError::InvalidCsvOptions(
+                "the delimiter and decimal separator can't be quotes or line breaks".to_owned(),
+            ));
+        }
+        if delimiter == self.decimal {
+            return Err(DwdError::InvalidCsvOptions(
+                "the delimiter and decimal separator have to be different".to_owned(),
+            ));
+        }
+        Ok(CsvOptions {
+            delimiter: delimiter as u8,
+            decimal: self.decimal,
+            bom: self.bom,
+        })
+    }
+}
+
+/// `name [unit]`, or just `name` for codes and values without a unit.
+fn header(name: &str, unit: Option<&str>) -> String {
+    match unit {
+        Some(unit) if unit != Unit::Code.symbol() => format!("{name} [{unit}]"),
+        _ => name.to_owned(),
+    }
+}
+
+fn cell(value: Option<&Value>, decimal: char) -> String {
+    match value {
+        // `Display` of f64 doesn't add `.0` to whole numbers (e.g. codes)
+        Some(Value::Number(n)) => match n.as_f64() {
+            Some(f) if n.is_f64() => f.to_string().replace('.', &decimal.to_string()),
+            _ => n.to_string(),
+        },
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn write_rows(
+    options: &CsvOptions,
+    headers: Vec<String>,
+    rows: impl Iterator<Item = Vec<String>>,
+) -> Result<Vec<u8>, DwdError> {
+    let mut out = Vec::new();
+    if options.bom {
+        out.extend_from_slice(UTF8_BOM);
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .from_writer(out);
+    writer
+        .write_record(headers)
+        .map_err(|_| DwdError::InternalError)?;
+    for row in rows {
+        writer
+            .write_record(row)
+            .map_err(|_| DwdError::InternalError)?;
+    }
+    writer.into_inner().map_err(|_| DwdError::InternalError)
+}
+
+/// One row per time step and one column per element (`time_steps` first, then sorted by key).
+/// Elements that aren't known (`?raw=true`) are appended without a unit.
+pub fn forecast_to_csv(forecast: &Forecast, query: &CsvQuery) -> Result<Vec<u8>, DwdError> {
+    let options = query.options()?;
+    let mut columns: Vec<(String, &Vec<Value>)> = Vec::new();
+    if let Some(time_steps) = forecast.data.get("time_steps") {
+        columns.push(("time_steps".to_owned(), time_steps));
+    }
+    let mut keys: Vec<&&str> = forecast
+        .data
+        .keys()
+        .filter(|&&key| key != "time_steps")
+        .collect();
+    keys.sort();
+    for key in keys {
+        let unit = forecast.units.get(*key).map(|u| u.symbol());
+        columns.push((header(key, unit), &forecast.data[*key]));
+    }
+    if let Some(raw) = &forecast.raw {
+        let mut names: Vec<&String> = raw.keys().collect();
+        names.sort();
+        columns.extend(names.into_iter().map(|name| (name.clone(), &raw[name])));
+    }
+
+    let headers = columns.iter().map(|(name, _)| name.clone()).collect();
+    let rows = (0..forecast.n_data_points).map(|i| {
+        columns
+            .iter()
+            .map(|(_, values)| cell(values.get(i), options.decimal))
+            .collect()
+    });
+    write_rows(&options, headers, rows)
+}
+
+/// One row per record and one column per parameter (`timestamp` first).
+/// Unknown columns (`extra`) are appended.
+pub fn report_to_csv(report: &WeatherReport, query: &CsvQuery) -> Result<Vec<u8>, DwdError> {
+    let options = query.options()?;
+    let params: BTreeSet<_> = report
+        .data
+        .iter()
+        .flat_map(|record| record.values.keys().copied())
+        .chain(report.units.keys().copied())
+        .collect();
+    let extra: BTreeSet<&String> = report
+        .data
+        .iter()
+        .flat_map(|record| record.extra.keys())
+        .collect();
+
+    let headers = std::iter::once("timestamp".to_owned())
+        .chain(params.iter().map(|param| {
+            header(
+                param.key(),
+                report.units.get(param).map(|unit| unit.symbol()),
+            )
+        }))
+        .chain(extra.iter().map(|name| {
+            header(
+                name,
+                report.extra_units.get(name.as_str()).map(String::as_str),
+            )
+        }))
+        .collect();
+    let rows = report.data.iter().map(|record| {
+        let timestamp = serde_json::to_value(record.timestamp).unwrap_or(Value::Null);
+        std::iter::once(cell(Some(&timestamp), '.'))
+            .chain(params.iter().map(|param| {
+                cell(
+                    record.values.get(param).map(|&v| Value::from(v)).as_ref(),
+                    options.decimal,
+                )
+            }))
+            .chain(
+                extra
+                    .iter()
+                    .map(|name| cell(record.extra.get(name.as_str()), options.decimal)),
+            )
+            .collect()
+    });
+    write_rows(&options, headers, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::ParseMode, time_format::Timestamp, weather_report::parse_weather_report,
+    };
+    use std::collections::{BTreeMap, HashMap};
+
+    fn report() -> WeatherReport {
+        let csv = "surface observations;;dry_bulb_temperature_at_2_meter_above_ground;remark
+Parameter description;;Grad C;-
+Datum;Uhrzeit (UTC);Temperatur;Bemerkung
+24.10.26;21:00;12,3;light, variable
+";
+        parse_weather_report(csv.as_bytes(), ParseMode::Strict).unwrap()
+    }
+
+    fn query(delimiter: Option<char>, decimal: char, bom: bool) -> CsvQuery {
+        CsvQuery {
+            delimiter,
+            decimal,
+            bom,
+        }
+    }
+
+    fn forecast() -> Forecast {
+        Forecast {
+            name: "10865".to_owned(),
+            description: String::new(),
+            issuer: String::new(),
+            generating_process: String::new(),
+            issue_time: Timestamp::from_millis(0),
+            reference_models: Vec::new(),
+            coordinates: String::new(),
+            location: None,
+            data: HashMap::from([
+                ("time_steps", vec![Value::from(0), Value::from(3_600_000)]),
+                ("temp", vec![Value::from(288.15), Value::Null]),
+                (
+                    "significant_weather",
+                    vec![Value::from(61.0), Value::from(3.0)],
+                ),
+            ]),
+            units: BTreeMap::from([("temp", Unit::Kelvin), ("significant_weather", Unit::Code)]),
+            n_data_points: 2,
+            weather: None,
+            raw: Some(HashMap::from([(
+                "XYZ".to_owned(),
+                vec![Value::from(1.5), Value::from(2.5)],
+            )])),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn to_csv(query: CsvQuery) -> Result<String, DwdError> {
+        report_to_csv(&report(), &query).map(|csv| String::from_utf8(csv).unwrap())
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(
+            to_csv(query(None, '.', false)).unwrap(),
+            "timestamp,temp [°C],remark\n1792875600000,12.3,\"light, variable\"\n"
+        );
+    }
+
+    #[test]
+    fn forecast_columns() {
+        let csv = forecast_to_csv(&forecast(), &query(None, ',', false)).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "time_steps;significant_weather;temp [K];XYZ\n0;61;288,15;1,5\n3600000;3;;2,5\n"
+        );
+    }
+
+    #[test]
+    fn query_parsing() {
+        let parsed = CsvQuery::from_query("format=csv&delimiter=%09&bom=true").unwrap();
+        assert_eq!(
+            (parsed.delimiter, parsed.decimal, parsed.bom),
+            (Some('\t'), '.', true)
+        );
+
+        let Err(error @ DwdError::InvalidCsvOptions(_)) = CsvQuery::from_query("delimiter=ab")
+        else {
+            panic!("expected invalid CSV options");
+        };
+        let response = actix_web::ResponseError::error_response(&error);
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn decimal_comma_defaults_to_semicolons() {
+        assert_eq!(
+            to_csv(query(None, ',', false)).unwrap(),
+            "timestamp;temp [°C];remark\n1792875600000;12,3;light, variable\n"
+        );
+    }
+
+    #[test]
+    fn custom_delimiter() {
+        assert_eq!(
+            to_csv(query(Some('\t'), '.', false)).unwrap(),
+            "timestamp\ttemp [°C]\tremark\n1792875600000\t12.3\tlight, variable\n"
+        );
+    }
+
+    #[test]
+    fn bom() {
+        let csv = report_to_csv(&report(), &query(None, '.', true)).unwrap();
+        assert!(csv.starts_with(UTF8_BOM));
+        assert!(csv[UTF8_BOM.len()..].starts_with(b"timestamp,"));
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        for query in [
+            query(Some(','), ',', false),
+            query(Some(';'), ';', false),
+            query(Some('§'), '.', false),
+            query(Some('"'), '.', false),
+            query(None, '\n', false),
+        ] {
+            let error = to_csv(query).unwrap_err();
+            assert!(matches!(error, DwdError::InvalidCsvOptions(_)));
+            assert_eq!(
+                actix_web::ResponseError::status_code(&error),
+                actix_web::http::StatusCode::BAD_REQUEST
+            );
+        }
+    }
+}
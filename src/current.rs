@@ -0,0 +1,231 @@
+use crate::{
+    diagnostics::{ParseOptions, ParseWarning},
+    time_format::{TimeFormatter, Timestamp},
+    timeline::{self, harmonize, TimelinePoint, TimelineSource},
+    units::{Unit, UnitSystem},
+    weather_code::{decode_present_weather, WeatherCondition},
+    weather_forecast::{get_forecast, Forecast},
+    weather_report::{get_weather_report, WeatherReport},
+    DwdError,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::{
+    openapi::{Object, Ref},
+    ToSchema,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct CurrentConditions {
+    pub name: String,
+    pub description: String,
+    /// Time of the latest observation
+    pub observation_time: Option<Timestamp>,
+    /// Seconds since the latest observation
+    pub observation_age: Option<i64>,
+    /// Time of the forecast time step used for missing values
+    pub forecast_time: Option<Timestamp>,
+    #[schema(schema_with = timeline::units_schema)]
+    pub units: BTreeMap<&'static str, Unit>,
+    #[schema(schema_with = timeline::values_schema)]
+    pub values: BTreeMap<&'static str, f64>,
+    #[schema(schema_with = sources_schema)]
+    pub sources: BTreeMap<&'static str, TimelineSource>,
+    #[schema(value_type = Option<WeatherCondition>)]
+    pub weather: Option<&'static WeatherCondition>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+fn sources_schema() -> Object {
+    timeline::keyed_schema(Ref::from_schema_name("TimelineSource"), true)
+        .description(Some("Where each value came from"))
+        .build()
+}
+
+pub async fn get_current(
+    station: &str,
+    options: ParseOptions,
+    system: UnitSystem,
+) -> Result<CurrentConditions, DwdError> {
+    let (report, forecast) = tokio::join!(
+        get_weather_report(station.to_owned(), options.mode),
+        get_forecast(station, options)
+    );
+    // not every MOSMIX station has reports
+    let report = match report {
+        Err(DwdError::NoReport) => WeatherReport::default(),
+        report => report?,
+    };
+    Ok(current_conditions(
+        report,
+        forecast?,
+        system,
+        Utc::now().timestamp_millis(),
+    ))
+}
+
+/// Takes the latest observation and fills missing values from the forecast time step closest to it
+/// (or to `now` if there are no observations).
+pub fn current_conditions(
+    report: WeatherReport,
+    forecast: Forecast,
+    system: UnitSystem,
+    now: i64,
+) -> CurrentConditions {
+    let harmonized = harmonize(&report, &forecast, system);
+    let observation = harmonized.observed.last();
+    let reference = observation.map(|p| p.timestamp.millis()).unwrap_or(now);
+    let forecast_step = harmonized
+        .forecast
+        .iter()
+        .min_by_key(|p| (p.timestamp.millis() - reference).abs());
+
+    let mut values = BTreeMap::new();
+    let mut sources = BTreeMap::new();
+    for point in forecast_step.into_iter().chain(observation) {
+        // observations come last and override the forecast
+        for (&key, &value) in &point.values {
+            values.insert(key, value);
+            sources.insert(key, point.source);
+        }
+    }
+    let timestamp = |point: Option<&TimelinePoint>| point.map(|p| p.timestamp);
+
+    let mut warnings = report.warnings;
+    warnings.extend(forecast.warnings);
+    CurrentConditions {
+        name: forecast.name,
+        description: forecast.description,
+        observation_time: timestamp(observation),
+        observation_age: observation.map(|p| (now - p.timestamp.millis()).div_euclid(1000)),
+        forecast_time: timestamp(forecast_step),
+        weather: values
+            .get("present_weather")
+            .and_then(|&ww| decode_present_weather(ww)),
+        units: harmonized.units,
+        values,
+        sources,
+        warnings,
+    }
+}
+
+impl CurrentConditions {
+    pub fn format_times(&mut self, formatter: TimeFormatter) {
+        for time in [&mut self.observation_time, &mut self.forecast_time]
+            .into_iter()
+            .flatten()
+        {
+            time.format_with(formatter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diagnostics::ParseMode, weather_report::parse_weather_report};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    const HOUR: i64 = 3_600_000;
+    /// 2026-10-24T21:00:00Z
+    const LATEST_OBSERVATION: i64 = 1792875600000;
+
+    fn report() -> WeatherReport {
+        let csv =
+            "surface observations;;dry_bulb_temperature_at_2_meter_above_ground;present_weather
+Parameter description;;Grad C;CODE_TABLE
+Datum;Uhrzeit (UTC);Temperatur;Wetter
+24.10.26;20:00;11,9;61
+24.10.26;21:00;12,3;---
+";
+        parse_weather_report(csv.as_bytes(), ParseMode::Strict).unwrap()
+    }
+
+    fn forecast() -> Forecast {
+        let time_steps = [-1, 0, 1].map(|h| Value::from(LATEST_OBSERVATION + h * HOUR));
+        Forecast {
+            name: "10865".to_owned(),
+            description: "MUENCHEN STADT".to_owned(),
+            issuer: String::new(),
+            generating_process: String::new(),
+            issue_time: Timestamp::from_millis(0),
+            reference_models: Vec::new(),
+            coordinates: String::new(),
+            location: None,
+            data: HashMap::from([
+                ("time_steps", time_steps.to_vec()),
+                ("temp", [285.15, 286.15, 287.15].map(Value::from).to_vec()),
+                ("wind_speed", [2.0, 3.0, 4.0].map(Value::from).to_vec()),
+                (
+                    "significant_weather",
+                    [3.0, 63.0, 61.0].map(Value::from).to_vec(),
+                ),
+            ]),
+            units: BTreeMap::from([
+                ("temp", Unit::Kelvin),
+                ("wind_speed", Unit::MetersPerSecond),
+                ("significant_weather", Unit::Code),
+            ]),
+            n_data_points: 3,
+            weather: None,
+            raw: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn latest_observation_with_forecast_fallback() {
+        let now = LATEST_OBSERVATION + HOUR / 2;
+        let current = current_conditions(report(), forecast(), UnitSystem::Metric, now);
+
+        assert_eq!(
+            current.observation_time.unwrap().millis(),
+            LATEST_OBSERVATION
+        );
+        assert_eq!(current.observation_age, Some(1800));
+        assert_eq!(current.forecast_time.unwrap().millis(), LATEST_OBSERVATION);
+        // the latest observation doesn't have a present weather, the older one isn't used
+        assert_eq!(
+            current.values,
+            BTreeMap::from([
+                ("temp", 12.3),
+                ("present_weather", 63.0),
+                ("wind_speed", 10.8)
+            ])
+        );
+        assert_eq!(
+            current.sources,
+            BTreeMap::from([
+                ("temp", TimelineSource::Observed),
+                ("present_weather", TimelineSource::Forecast),
+                ("wind_speed", TimelineSource::Forecast)
+            ])
+        );
+        assert_eq!(current.units["temp"], Unit::Celsius);
+        assert_eq!(current.units["wind_speed"], Unit::KilometersPerHour);
+        assert!(std::ptr::eq(
+            current.weather.unwrap(),
+            decode_present_weather(63.0).unwrap()
+        ));
+    }
+
+    #[test]
+    fn forecast_closest_to_now_without_observations() {
+        let now = LATEST_OBSERVATION + HOUR + 60_000;
+        let current = current_conditions(WeatherReport::default(), forecast(), UnitSystem::Si, now);
+
+        assert!(current.observation_time.is_none());
+        assert!(current.observation_age.is_none());
+        assert_eq!(
+            current.forecast_time.unwrap().millis(),
+            LATEST_OBSERVATION + HOUR
+        );
+        assert_eq!(current.values["temp"], 287.15);
+        assert!(current
+            .sources
+            .values()
+            .all(|&source| source == TimelineSource::Forecast));
+    }
+}
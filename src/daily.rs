@@ -0,0 +1,313 @@
+use crate::{
+    diagnostics::ParseWarning,
+    time_format::{TimeFormatter, Timestamp},
+    weather_code::{decode_present_weather, WeatherCondition},
+    weather_forecast::Forecast,
+};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::Europe::Berlin;
+
+#[derive(Serialize, ToSchema)]
+pub struct DailyForecast {
+    pub name: String,
+    #[schema(value_type = String)]
+    pub timezone: Tz,
+    pub issue_time: Timestamp,
+    pub days: Vec<DailySummary>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DailySummary {
+    /// Local calendar date (`YYYY-MM-DD`)
+    pub date: String,
+    /// Start of the local day (inclusive)
+    pub start: Timestamp,
+    /// End of the local day (exclusive), days aren't always 24h long
+    pub end: Timestamp,
+    pub n_time_steps: usize,
+    pub min_temp: Option<f64>,
+    pub max_temp: Option<f64>,
+    pub precipitation: Option<f64>,
+    pub max_wind_gust: Option<f64>,
+    pub sunshine_hours: Option<f64>,
+    pub significant_weather: Option<u8>,
+    pub max_precipitation_probability: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<WeatherCondition>)]
+    pub weather: Option<&'static WeatherCondition>,
+}
+
+/// Collects the values of each day.
+/// Instantaneous values (`temp`, `significant_weather`) belong to the day of their time step,
+/// values covering the preceding period (e.g. `precipitation_1h`) belong to the day that period started in.
+#[derive(Default)]
+struct DayValues {
+    n_time_steps: usize,
+    temp: Vec<f64>,
+    min_temp: Vec<f64>,
+    max_temp: Vec<f64>,
+    precipitation: Vec<f64>,
+    wind_gust: Vec<f64>,
+    sunshine: Vec<f64>,
+    significant_weather: Vec<u8>,
+    precipitation_probability: Vec<f64>,
+}
+
+pub fn aggregate_daily(forecast: &Forecast, tz: Tz) -> DailyForecast {
+    let time_steps = forecast.column("time_steps");
+    let temp = forecast.column("temp");
+    let min_temp = forecast.column("min_temp");
+    let max_temp = forecast.column("max_temp");
+    let precipitation = forecast.column("precipitation_1h_significant_weather");
+    let wind_gust = forecast.column("max_wind_gust_1h");
+    let sunshine = forecast.column("sunshine_last_hour");
+    let significant_weather = forecast.column("significant_weather");
+    let precipitation_probability = forecast.column("p_precipitation_1h");
+
+    let mut days = BTreeMap::<NaiveDate, DayValues>::new();
+    for (i, time) in time_steps.iter().enumerate() {
+        let Some(time) = time.and_then(|t| DateTime::<Utc>::from_timestamp_millis(t as i64)) else {
+            continue;
+        };
+        let day_of = |t: DateTime<Utc>| t.with_timezone(&tz).date_naive();
+        let instant_day = day_of(time);
+        // a value at 00:00 covering the last hour belongs to the previous day
+        let period_day = day_of(time - Duration::milliseconds(1));
+
+        let instant = days.entry(instant_day).or_default();
+        instant.n_time_steps += 1;
+        instant.temp.extend(temp[i]);
+        instant
+            .significant_weather
+            .extend(significant_weather[i].and_then(weather_code));
+
+        let period = days.entry(period_day).or_default();
+        period.min_temp.extend(min_temp[i]);
+        period.max_temp.extend(max_temp[i]);
+        period.precipitation.extend(precipitation[i]);
+        period.wind_gust.extend(wind_gust[i]);
+        period.sunshine.extend(sunshine[i]);
+        period
+            .precipitation_probability
+            .extend(precipitation_probability[i]);
+    }
+
+    DailyForecast {
+        name: forecast.name.clone(),
+        timezone: tz,
+        issue_time: forecast.issue_time,
+        days: days
+            .into_iter()
+            .filter(|(_, values)| values.n_time_steps > 0)
+            .map(|(date, values)| summarize(date, values, tz))
+            .collect(),
+        warnings: forecast.warnings.clone(),
+    }
+}
+
+fn summarize(date: NaiveDate, values: DayValues, tz: Tz) -> DailySummary {
+    let min = |v: &[f64]| v.iter().copied().reduce(f64::min);
+    let max = |v: &[f64]| v.iter().copied().reduce(f64::max);
+    let sum = |v: &[f64]| (!v.is_empty()).then(|| v.iter().sum::<f64>());
+
+    DailySummary {
+        date: date.format("%Y-%m-%d").to_string(),
+        start: start_of_day(date, tz),
+        end: start_of_day(date.succ_opt().unwrap_or(date), tz),
+        n_time_steps: values.n_time_steps,
+        min_temp: min(&values.min_temp).or_else(|| min(&values.temp)),
+        max_temp: max(&values.max_temp).or_else(|| max(&values.temp)),
+        precipitation: sum(&values.precipitation),
+        max_wind_gust: max(&values.wind_gust),
+        sunshine_hours: sum(&values.sunshine).map(|s| s / 3600.0),
+        significant_weather: dominant_weather(&values.significant_weather),
+        max_precipitation_probability: max(&values.precipitation_probability),
+        weather: None,
+    }
+}
+
+/// Local midnight in epoch milliseconds. If midnight doesn't exist (DST gap), the first valid instant is used.
+fn start_of_day(date: NaiveDate, tz: Tz) -> Timestamp {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    (0..=3)
+        .find_map(|h| {
+            tz.from_local_datetime(&(midnight + Duration::hours(h)))
+                .earliest()
+        })
+        .map(|t| t.timestamp_millis())
+        .map(Timestamp::from_millis)
+        .unwrap_or_else(|| Timestamp::from_millis(midnight.and_utc().timestamp_millis()))
+}
+
+/// A present weather code (0..=99), values that aren't one are skipped.
+fn weather_code(ww: f64) -> Option<u8> {
+    let ww = ww.round();
+    if !(0.0..=99.0).contains(&ww) {
+        return None;
+    }
+    u8::try_from(ww as i64).ok()
+}
+
+/// Any significant weather (fog, precipitation, thunderstorms - higher codes are more significant) takes precedence
+/// over the cloud cover codes (0..=3), for which the most frequent one is used.
+fn dominant_weather(codes: &[u8]) -> Option<u8> {
+    codes
+        .iter()
+        .copied()
+        .filter(|&ww| ww > 3)
+        .max()
+        .or_else(|| {
+            codes
+                .iter()
+                .copied()
+                .counts()
+                .into_iter()
+                .max_by_key(|&(ww, count)| (count, ww))
+                .map(|(ww, _)| ww)
+        })
+}
+
+impl DailyForecast {
+    pub fn format_times(&mut self, formatter: TimeFormatter) {
+        self.issue_time.format_with(formatter);
+        for day in &mut self.days {
+            day.start.format_with(formatter);
+            day.end.format_with(formatter);
+        }
+    }
+}
+
+pub fn decode_daily_weather(daily: &mut DailyForecast) {
+    for day in &mut daily.days {
+        day.weather = day
+            .significant_weather
+            .and_then(|ww| decode_present_weather(ww as f64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weather_forecast::Coordinates;
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    fn millis(rfc3339: &str) -> i64 {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    /// Hourly time steps from `first` to `last` (inclusive), 1mm of precipitation in each hour.
+    fn hourly_forecast(first: &str, last: &str) -> Forecast {
+        let time_steps: Vec<Value> = (millis(first)..=millis(last))
+            .step_by(3_600_000)
+            .map(Value::from)
+            .collect();
+        let n = time_steps.len();
+        Forecast {
+            name: "TEST".to_owned(),
+            description: String::new(),
+            issuer: String::new(),
+            generating_process: String::new(),
+            issue_time: Timestamp::from_millis(0),
+            reference_models: Vec::new(),
+            coordinates: String::new(),
+            location: Some(Coordinates {
+                longitude: 0.0,
+                latitude: 0.0,
+                elevation: None,
+            }),
+            data: HashMap::from([
+                ("time_steps", time_steps),
+                (
+                    "precipitation_1h_significant_weather",
+                    vec![Value::from(1.0); n],
+                ),
+            ]),
+            units: BTreeMap::new(),
+            n_data_points: n,
+            weather: None,
+            raw: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn day<'a>(daily: &'a DailyForecast, date: &str) -> &'a DailySummary {
+        daily.days.iter().find(|d| d.date == date).unwrap()
+    }
+
+    #[test]
+    fn short_day_at_dst_start() {
+        // local midnight of 2024-03-31 is 23:00 UTC, of 2024-04-01 22:00 UTC
+        let forecast = hourly_forecast("2024-03-30T23:00:00Z", "2024-03-31T22:00:00Z");
+        let daily = aggregate_daily(&forecast, DEFAULT_TIMEZONE);
+
+        let day = day(&daily, "2024-03-31");
+        assert_eq!(day.start.millis(), millis("2024-03-30T23:00:00Z"));
+        assert_eq!(day.end.millis(), millis("2024-03-31T22:00:00Z"));
+        assert_eq!(day.n_time_steps, 23);
+        // the value at 00:00 covers the previous day, the one at 24:00 this day
+        assert_eq!(day.precipitation, Some(23.0));
+    }
+
+    #[test]
+    fn long_day_at_dst_end() {
+        // local midnight of 2024-10-27 is 22:00 UTC, of 2024-10-28 23:00 UTC
+        let forecast = hourly_forecast("2024-10-26T22:00:00Z", "2024-10-27T23:00:00Z");
+        let daily = aggregate_daily(&forecast, DEFAULT_TIMEZONE);
+
+        let day = day(&daily, "2024-10-27");
+        assert_eq!(day.start.millis(), millis("2024-10-26T22:00:00Z"));
+        assert_eq!(day.end.millis(), millis("2024-10-27T23:00:00Z"));
+        assert_eq!(day.n_time_steps, 25);
+        assert_eq!(day.precipitation, Some(25.0));
+    }
+
+    #[test]
+    fn period_values_at_midnight_belong_to_the_previous_day() {
+        let forecast = hourly_forecast("2024-06-10T22:00:00Z", "2024-06-10T22:00:00Z");
+        let daily = aggregate_daily(&forecast, DEFAULT_TIMEZONE);
+
+        // 00:00 local is an instant of the 11th, its precipitation fell on the 10th
+        assert_eq!(daily.days.len(), 1);
+        assert_eq!(daily.days[0].date, "2024-06-11");
+        assert_eq!(daily.days[0].n_time_steps, 1);
+        assert_eq!(daily.days[0].precipitation, None);
+    }
+
+    #[test]
+    fn invalid_weather_codes_are_skipped() {
+        assert_eq!(weather_code(61.0), Some(61));
+        assert_eq!(weather_code(60.9), Some(61));
+        assert_eq!(weather_code(0.0), Some(0));
+        assert_eq!(weather_code(99.0), Some(99));
+        for ww in [-1.0, 100.0, 300.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(weather_code(ww), None, "{ww}");
+        }
+
+        let mut forecast = hourly_forecast("2024-06-10T06:00:00Z", "2024-06-10T08:00:00Z");
+        let codes = [-1.0, 300.0, 2.0].map(Value::from).to_vec();
+        forecast.data.insert("significant_weather", codes);
+        let daily = aggregate_daily(&forecast, DEFAULT_TIMEZONE);
+        assert_eq!(day(&daily, "2024-06-10").significant_weather, Some(2));
+    }
+
+    #[test]
+    fn dominant_weather_tie_breaking() {
+        assert_eq!(dominant_weather(&[]), None);
+        // significant weather wins over cloud cover, the highest code over more frequent ones
+        assert_eq!(dominant_weather(&[1, 1, 1, 61, 3]), Some(61));
+        assert_eq!(dominant_weather(&[95, 61, 61, 45]), Some(95));
+        // cloud cover: the most frequent code, ties go to the higher (cloudier) one
+        assert_eq!(dominant_weather(&[0, 0, 2, 3]), Some(0));
+        assert_eq!(dominant_weather(&[1, 1, 2, 2]), Some(2));
+    }
+}
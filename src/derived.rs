@@ -0,0 +1,245 @@
+use crate::{elements::DERIVED_ELEMENTS, query::comma_separated, units::Unit};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::{IntoParams, ToSchema};
+
+const KELVIN: f64 = 273.15;
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DerivedQuery {
+    /// Comma separated [`DerivedQuantity`]s
+    #[serde(default, deserialize_with = "comma_separated")]
+    #[param(value_type = Option<String>)]
+    pub derived: Vec<DerivedQuantity>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DerivedQuantity {
+    RelativeHumidity,
+    ApparentTemperature,
+    WindChill,
+    HeatIndex,
+    Beaufort,
+}
+
+impl DerivedQuantity {
+    pub fn key(self) -> &'static str {
+        match self {
+            DerivedQuantity::RelativeHumidity => "relative_humidity",
+            DerivedQuantity::ApparentTemperature => "apparent_temperature",
+            DerivedQuantity::WindChill => "wind_chill",
+            DerivedQuantity::HeatIndex => "heat_index",
+            DerivedQuantity::Beaufort => "beaufort",
+        }
+    }
+
+    /// `temp` and `dew_point` are in K, `wind_speed` in m/s.
+    /// Temperatures are returned in K to match the other forecast elements.
+    fn compute(
+        self,
+        temp: Option<f64>,
+        dew_point: Option<f64>,
+        wind_speed: Option<f64>,
+    ) -> Option<f64> {
+        let temp = temp.map(|t| t - KELVIN);
+        let rh = || relative_humidity(temp?, dew_point? - KELVIN);
+        match self {
+            DerivedQuantity::RelativeHumidity => rh(),
+            DerivedQuantity::ApparentTemperature => {
+                Some(apparent_temperature(temp?, rh()?, wind_speed?) + KELVIN)
+            }
+            DerivedQuantity::WindChill => Some(wind_chill(temp?, wind_speed? * 3.6) + KELVIN),
+            DerivedQuantity::HeatIndex => Some(heat_index(temp?, rh()?) + KELVIN),
+            DerivedQuantity::Beaufort => Some(beaufort(wind_speed?) as f64),
+        }
+    }
+}
+
+/// Adds the requested quantities as additional columns.
+/// A time step is `null` if any of its inputs is `null`.
+pub fn add_derived(
+    data: &mut HashMap<&'static str, Vec<Value>>,
+    units: &mut BTreeMap<&'static str, Unit>,
+    quantities: &[DerivedQuantity],
+) {
+    let column = |key: &str| -> Vec<Option<f64>> {
+        data.get(key)
+            .map(|values| values.iter().map(Value::as_f64).collect())
+            .unwrap_or_default()
+    };
+    let temp = column("temp");
+    let dew_point = column("dew_point");
+    let wind_speed = column("wind_speed");
+    let n_time_steps = data.get("time_steps").map(Vec::len).unwrap_or_default();
+
+    for quantity in quantities {
+        let values = (0..n_time_steps)
+            .map(|i| {
+                let get = |col: &[Option<f64>]| col.get(i).copied().flatten();
+                quantity
+                    .compute(get(&temp), get(&dew_point), get(&wind_speed))
+                    .map(Value::from)
+                    .unwrap_or(Value::Null)
+            })
+            .collect();
+        data.insert(quantity.key(), values);
+        if let Some(element) = DERIVED_ELEMENTS.get(quantity.key()) {
+            units.insert(element.key, element.unit);
+        }
+    }
+}
+
+/// Saturation vapour pressure over water in hPa (Magnus formula, Alduchov & Eskridge 1996).
+fn saturation_vapour_pressure(temp: f64) -> f64 {
+    6.1094 * (17.625 * temp / (temp + 243.04)).exp()
+}
+
+/// Relative humidity in % from temperature and dew point in °C.
+pub fn relative_humidity(temp: f64, dew_point: f64) -> Option<f64> {
+    let rh = 100.0 * saturation_vapour_pressure(dew_point) / saturation_vapour_pressure(temp);
+    rh.is_finite().then(|| rh.clamp(0.0, 100.0))
+}
+
+/// Apparent temperature in °C as used by the Australian Bureau of Meteorology (Steadman 1994, without radiation):
+/// `AT = Ta + 0.33 e - 0.70 ws - 4.00` with the water vapour pressure `e` in hPa and wind speed `ws` in m/s.
+pub fn apparent_temperature(temp: f64, relative_humidity: f64, wind_speed: f64) -> f64 {
+    let e = relative_humidity / 100.0 * 6.105 * (17.27 * temp / (237.7 + temp)).exp();
+    temp + 0.33 * e - 0.70 * wind_speed - 4.00
+}
+
+/// Wind chill in °C (JAG/TI, used by the NWS and Environment Canada) with the wind speed in km/h:
+/// `WCT = 13.12 + 0.6215 T - 11.37 V^0.16 + 0.3965 T V^0.16`.
+/// It's only defined for `T <= 10 °C` and `V >= 4.8 km/h`, otherwise the air temperature is returned.
+pub fn wind_chill(temp: f64, wind_speed: f64) -> f64 {
+    if temp > 10.0 || wind_speed < 4.8 {
+        return temp;
+    }
+    let v = wind_speed.powf(0.16);
+    13.12 + 0.6215 * temp - 11.37 * v + 0.3965 * temp * v
+}
+
+/// Heat index in °C following the NWS algorithm (Steadman's simple formula, Rothfusz regression with adjustments).
+/// Below 80 °F (26.7 °C) the result approaches the air temperature.
+pub fn heat_index(temp: f64, relative_humidity: f64) -> f64 {
+    let t = temp * 9.0 / 5.0 + 32.0;
+    let rh = relative_humidity;
+
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let hi = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.04901523 * t + 10.14333127 * rh
+            - 0.22475541 * t * rh
+            - 0.00683783 * t * t
+            - 0.05481717 * rh * rh
+            + 0.00122874 * t * t * rh
+            + 0.00085282 * t * rh * rh
+            - 0.00000199 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            hi += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+        }
+        hi
+    };
+
+    (hi - 32.0) * 5.0 / 9.0
+}
+
+/// Lower bounds (in m/s) of the Beaufort forces 1 to 12.
+const BEAUFORT_SCALE: [f64; 12] = [
+    0.3, 1.6, 3.4, 5.5, 8.0, 10.8, 13.9, 17.2, 20.8, 24.5, 28.5, 32.7,
+];
+
+/// Beaufort force (0..=12) for a wind speed in m/s.
+pub fn beaufort(wind_speed: f64) -> u8 {
+    BEAUFORT_SCALE
+        .iter()
+        .take_while(|&&bound| wind_speed >= bound)
+        .count() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn relative_humidity_reference() {
+        assert_close(relative_humidity(20.0, 10.0).unwrap(), 52.5, 0.1);
+        assert_close(relative_humidity(0.0, -5.0).unwrap(), 69.1, 0.1);
+        assert_close(relative_humidity(15.0, 15.0).unwrap(), 100.0, 1e-9);
+    }
+
+    #[test]
+    fn apparent_temperature_reference() {
+        assert_close(apparent_temperature(25.0, 50.0, 2.0), 24.8, 0.1);
+        assert_close(apparent_temperature(10.0, 80.0, 5.0), 5.7, 0.1);
+    }
+
+    #[test]
+    fn wind_chill_reference() {
+        // Environment Canada wind chill chart
+        assert_close(wind_chill(-10.0, 30.0), -19.5, 0.1);
+        assert_close(wind_chill(0.0, 20.0), -5.2, 0.1);
+        // outside the domain
+        assert_eq!(wind_chill(15.0, 30.0), 15.0);
+        assert_eq!(wind_chill(-5.0, 3.0), -5.0);
+    }
+
+    #[test]
+    fn heat_index_reference() {
+        // NWS heat index chart: 90 °F at 70 % -> 106 °F, 80 °F at 40 % -> 80 °F
+        assert_close(
+            heat_index((90.0 - 32.0) * 5.0 / 9.0, 70.0),
+            (106.0 - 32.0) * 5.0 / 9.0,
+            0.5,
+        );
+        assert_close(
+            heat_index((80.0 - 32.0) * 5.0 / 9.0, 40.0),
+            (80.0 - 32.0) * 5.0 / 9.0,
+            0.5,
+        );
+    }
+
+    #[test]
+    fn beaufort_reference() {
+        assert_eq!(beaufort(0.0), 0);
+        assert_eq!(beaufort(0.3), 1);
+        assert_eq!(beaufort(5.4), 3);
+        assert_eq!(beaufort(5.5), 4);
+        assert_eq!(beaufort(20.0), 8);
+        assert_eq!(beaufort(40.0), 12);
+    }
+
+    #[test]
+    fn derived_columns_handle_nulls() {
+        let mut data = HashMap::<&'static str, Vec<Value>>::new();
+        data.insert("time_steps", vec![Value::from(0), Value::from(1)]);
+        data.insert("temp", vec![Value::from(293.15), Value::Null]);
+        data.insert("dew_point", vec![Value::from(283.15), Value::from(280.0)]);
+        data.insert("wind_speed", vec![Value::Null, Value::from(4.0)]);
+
+        let mut units = BTreeMap::new();
+        add_derived(
+            &mut data,
+            &mut units,
+            &[DerivedQuantity::RelativeHumidity, DerivedQuantity::Beaufort],
+        );
+
+        let rh = &data["relative_humidity"];
+        assert_close(rh[0].as_f64().unwrap(), 52.5, 0.1);
+        assert!(rh[1].is_null());
+        assert_eq!(data["beaufort"], vec![Value::Null, Value::from(3.0)]);
+        assert_eq!(units["relative_humidity"], Unit::Percent);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::DwdError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ParseQuery {
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub raw: bool,
+}
+
+impl ParseQuery {
+    pub fn mode(&self) -> ParseMode {
+        if self.strict {
+            ParseMode::Strict
+        } else {
+            ParseMode::Lenient
+        }
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        ParseOptions {
+            mode: self.mode(),
+            passthrough: self.raw,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+    /// Keep unknown forecast elements (by their DWD name) instead of skipping them
+    pub passthrough: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Skip anything that can't be parsed and report it as a warning
+    #[default]
+    Lenient,
+    /// Fail on the first thing that can't be parsed
+    Strict,
+}
+
+/// Something a parser skipped.
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParseWarning {
+    /// A CSV row of a report that couldn't be read or had an invalid date (`line` is 1-based)
+    SkippedRow { line: u64, reason: String },
+    /// A forecast element that isn't known (not reported with `passthrough`)
+    UnknownElement { element: String },
+    /// A forecast element that doesn't have a value for each time step
+    LengthMismatch {
+        element: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The coordinates of a forecast that couldn't be parsed (its `location` is `null`)
+    InvalidCoordinates { coordinates: String },
+    /// A forecast time step (skipped with its values) or reference time that couldn't be parsed
+    InvalidTimestamp { timestamp: String },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::SkippedRow { line, reason } => {
+                write!(f, "skipped row on line {line}: {reason}")
+            }
+            ParseWarning::UnknownElement { element } => write!(f, "unknown element {element}"),
+            ParseWarning::LengthMismatch {
+                element,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "element {element} has {actual} values but there are {expected} time steps"
+            ),
+            ParseWarning::InvalidCoordinates { coordinates } => {
+                write!(f, "invalid coordinates {coordinates:?}")
+            }
+            ParseWarning::InvalidTimestamp { timestamp } => {
+                write!(f, "invalid timestamp {timestamp:?}")
+            }
+        }
+    }
+}
+
+/// Collects the warnings of a parser or turns them into errors in [`ParseMode::Strict`].
+pub struct Diagnostics {
+    mode: ParseMode,
+    warnings: Vec<ParseWarning>,
+}
+
+impl Diagnostics {
+    pub fn new(mode: ParseMode) -> Self {
+        Self {
+            mode,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn warn(&mut self, warning: ParseWarning) -> Result<(), DwdError> {
+        match self.mode {
+            ParseMode::Lenient => {
+                self.warnings.push(warning);
+                Ok(())
+            }
+            ParseMode::Strict => Err(DwdError::StrictParsing(warning)),
+        }
+    }
+
+    pub fn into_warnings(self) -> Vec<ParseWarning> {
+        self.warnings
+    }
+}
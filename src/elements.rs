@@ -0,0 +1,234 @@
+use crate::{
+    query::comma_separated,
+    units::{Quantity, Unit},
+};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ElementsQuery {
+    #[serde(default)]
+    pub keys: KeyScheme,
+    /// Elements by their key or DWD name, all elements if empty
+    #[serde(default, deserialize_with = "comma_separated")]
+    #[param(value_type = Option<String>)]
+    pub elements: Vec<String>,
+}
+
+/// How elements are keyed in the response.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyScheme {
+    /// The keys of this API (e.g. `temp`)
+    #[default]
+    Friendly,
+    /// The DWD names (`elementName`, e.g. `TTT`)
+    Dwd,
+}
+
+pub struct ForecastElement {
+    /// The key used in the response
+    pub key: &'static str,
+    /// The unit the DWD delivers the values in
+    pub unit: Unit,
+    pub quantity: Quantity,
+}
+
+const fn element(key: &'static str, unit: Unit, quantity: Quantity) -> ForecastElement {
+    ForecastElement {
+        key,
+        unit,
+        quantity,
+    }
+}
+
+/// All known MOSMIX elements by their DWD name (`elementName`).
+/// See https://opendata.dwd.de/weather/lib/MetElementDefinition.xml
+pub static KML_ELEMENTS: phf::Map<&'static str, ForecastElement> = phf::phf_map! {
+    "TTT" => element("temp", Unit::Kelvin, Quantity::Temperature),
+    "Td" => element("dew_point", Unit::Kelvin, Quantity::Temperature),
+    "TX" => element("max_temp", Unit::Kelvin, Quantity::Temperature),
+    "TN" => element("min_temp", Unit::Kelvin, Quantity::Temperature),
+    "DD" => element("wind_direction", Unit::Degrees, Quantity::Direction),
+    "FF" => element("wind_speed", Unit::MetersPerSecond, Quantity::Speed),
+    "FX1" => element("max_wind_gust_1h", Unit::MetersPerSecond, Quantity::Speed),
+    "FX3" => element("max_wind_gust_3h", Unit::MetersPerSecond, Quantity::Speed),
+    "FXh" => element("max_wind_gust_12h", Unit::MetersPerSecond, Quantity::Speed),
+    "RR1c" => element("precipitation_1h_significant_weather", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RR1" => element("precipitation_1h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RR3c" => element("precipitation_3h_significant_weather", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RR3" => element("precipitation_3h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RRS1c" => element("snow_rain_eq_1h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RRS3c" => element("snow_rain_eq_3h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "ww" => element("significant_weather", Unit::Code, Quantity::Code),
+    "W1W2" => element("past_weather_6h", Unit::Code, Quantity::Code),
+    "N" => element("total_cloud_cover", Unit::Percent, Quantity::Percent),
+    "Neff" => element("effective_cloud_cover", Unit::Percent, Quantity::Percent),
+    "N05" => element("cloud_cover_500ft", Unit::Percent, Quantity::Percent),
+    "Nl" => element("low_cloud_cover", Unit::Percent, Quantity::Percent),
+    "Nm" => element("midlevel_cloud_cover", Unit::Percent, Quantity::Percent),
+    "Nh" => element("high_cloud_cover", Unit::Percent, Quantity::Percent),
+    "PPPP" => element("surface_pressure", Unit::Pascal, Quantity::Pressure),
+    "T5cm" => element("temp_5cm", Unit::Kelvin, Quantity::Temperature),
+    "RadS3" => element("shortwave_radiation_3h", Unit::KilojoulesPerSquareMeter, Quantity::Radiation),
+    "Rad1h" => element("global_irradiance", Unit::KilojoulesPerSquareMeter, Quantity::Irradiance),
+    "RadL3" => element("longwave_radiation_3h", Unit::KilojoulesPerSquareMeter, Quantity::Radiation),
+    "VV" => element("visibility", Unit::Meters, Quantity::Visibility),
+    "SunD1" => element("sunshine_last_hour", Unit::Seconds, Quantity::Duration),
+    "FXh25" => element("p_wind_gust_25kn_12h", Unit::Percent, Quantity::Percent),
+    "FXh40" => element("p_wind_gust_40kn_12h", Unit::Percent, Quantity::Percent),
+    "FXh55" => element("p_wind_gust_55kn_12h", Unit::Percent, Quantity::Percent),
+    "wwM" => element("p_fog_1h", Unit::Percent, Quantity::Percent),
+    "wwM6" => element("p_fog_6h", Unit::Percent, Quantity::Percent),
+    "wwMh" => element("p_fog_12h", Unit::Percent, Quantity::Percent),
+    "Rh00" => element("p_precipitation_0mm_12h", Unit::Percent, Quantity::Percent),
+    "R602" => element("p_precipitation_p2mm_6h", Unit::Percent, Quantity::Percent),
+    "Rh02" => element("p_precipitation_p2mm_12h", Unit::Percent, Quantity::Percent),
+    "Rd02" => element("p_precipitation_p2mm_24h", Unit::Percent, Quantity::Percent),
+    "Rh10" => element("p_precipitation_1mm_12h", Unit::Percent, Quantity::Percent),
+    "R650" => element("p_precipitation_5mm_6h", Unit::Percent, Quantity::Percent),
+    "Rh50" => element("p_precipitation_5mm_12h", Unit::Percent, Quantity::Percent),
+    "Rd50" => element("p_precipitation_5mm_24h", Unit::Percent, Quantity::Percent),
+    "TG" => element("min_temp_5cm_12h", Unit::Kelvin, Quantity::Temperature),
+    "TM" => element("mean_temp_24h", Unit::Kelvin, Quantity::Temperature),
+    "DRR1" => element("precipitation_duration_1h", Unit::Seconds, Quantity::Duration),
+    "wwZ" => element("p_drizzle_1h", Unit::Percent, Quantity::Percent),
+    "wwD" => element("p_straitform_precipitation_1h", Unit::Percent, Quantity::Percent),
+    "wwC" => element("p_convective_precipitation_1h", Unit::Percent, Quantity::Percent),
+    "wwT" => element("p_thunderstorms_1h", Unit::Percent, Quantity::Percent),
+    "wwL" => element("p_liquid_precipitation_1h", Unit::Percent, Quantity::Percent),
+    "wwS" => element("p_solid_precipitation_1h", Unit::Percent, Quantity::Percent),
+    "wwF" => element("p_freezing_rain_1h", Unit::Percent, Quantity::Percent),
+    "wwP" => element("p_precipitation_1h", Unit::Percent, Quantity::Percent),
+    "VV10" => element("p_visibility_below_1km", Unit::Percent, Quantity::Percent),
+    "E_TTT" => element("e_temp", Unit::Kelvin, Quantity::TemperatureDifference),
+    "E_FF" => element("e_wind_speed", Unit::MetersPerSecond, Quantity::Speed),
+    "E_DD" => element("e_wind_direction", Unit::Degrees, Quantity::Direction),
+    "E_Td" => element("e_dew_point", Unit::Kelvin, Quantity::TemperatureDifference),
+    "RR6" => element("precipitation_6h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RR6c" => element("precipitation_6h_significant_weather", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "R600" => element("p_precipitation_0mm_6h", Unit::Percent, Quantity::Percent),
+    "R101" => element("p_precipitation_p1mm_1h", Unit::Percent, Quantity::Percent),
+    "R102" => element("p_precipitation_p2mm_1h", Unit::Percent, Quantity::Percent),
+    "R103" => element("p_precipitation_p3mm_1h", Unit::Percent, Quantity::Percent),
+    "R105" => element("p_precipitation_p5mm_1h", Unit::Percent, Quantity::Percent),
+    "R107" => element("p_precipitation_p7mm_1h", Unit::Percent, Quantity::Percent),
+    "R110" => element("p_precipitation_1mm_1h", Unit::Percent, Quantity::Percent),
+    "R120" => element("p_precipitation_2mm_1h", Unit::Percent, Quantity::Percent),
+    "SunD" => element("sunshine_duration_yesterday", Unit::Seconds, Quantity::Duration),
+    "RSunD" => element("rel_sunshine_duration_24h", Unit::Percent, Quantity::Percent),
+    "PSd00" => element("p_rel_sunshine_duration_24h", Unit::Percent, Quantity::Percent),
+    "PSd30" => element("p_rel_sunshine_duration_30p_24h", Unit::Percent, Quantity::Percent),
+    "PSd60" => element("p_rel_sunshine_duration_60p_24h", Unit::Percent, Quantity::Percent),
+    "RRad1" => element("global_irradiance_1h", Unit::Percent, Quantity::Percent),
+    "PEvap" => element("potential_evapotranspiration_24h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "R130" => element("p_precipitation_3mm_1h", Unit::Percent, Quantity::Percent),
+    "R150" => element("p_precipitation_5mm_1h", Unit::Percent, Quantity::Percent),
+    "RR1o1" => element("p_precipitation_10mm_1h", Unit::Percent, Quantity::Percent),
+    "RR1w1" => element("p_precipitation_15mm_1h", Unit::Percent, Quantity::Percent),
+    "RR1u1" => element("p_precipitation_25mm_1h", Unit::Percent, Quantity::Percent),
+    "wwD6" => element("p_straightform_precipitation_6h", Unit::Percent, Quantity::Percent),
+    "wwC6" => element("p_convective_precipitation_6h", Unit::Percent, Quantity::Percent),
+    "wwT6" => element("p_thunderstorms_6h", Unit::Percent, Quantity::Percent),
+    "wwP6" => element("p_precipitation_6h", Unit::Percent, Quantity::Percent),
+    "wwL6" => element("p_liquid_precipitation_6h", Unit::Percent, Quantity::Percent),
+    "wwF6" => element("p_freezing_rain_6h", Unit::Percent, Quantity::Percent),
+    "wwS6" => element("p_solid_precipitation_6h", Unit::Percent, Quantity::Percent),
+    "wwZ6" => element("p_drizzle_6h", Unit::Percent, Quantity::Percent),
+    "wwMd" => element("p_fog_24h", Unit::Percent, Quantity::Percent),
+    "FX625" => element("p_gusts_25kn_6h", Unit::Percent, Quantity::Percent),
+    "FX640" => element("p_gusts_40kn_6h", Unit::Percent, Quantity::Percent),
+    "FX655" => element("p_gusts_55kn_6h", Unit::Percent, Quantity::Percent),
+    "wwDh" => element("p_straightform_precipitation_12h", Unit::Percent, Quantity::Percent),
+    "wwCh" => element("p_convective_precipitation_12h", Unit::Percent, Quantity::Percent),
+    "wwTh" => element("p_thunderstorms_12h", Unit::Percent, Quantity::Percent),
+    "wwPh" => element("p_precipitation_12h", Unit::Percent, Quantity::Percent),
+    "wwLh" => element("p_liquid_precipitation_12h", Unit::Percent, Quantity::Percent),
+    "wwFh" => element("p_freezing_rain_12h", Unit::Percent, Quantity::Percent),
+    "wwSh" => element("p_solid_precipitation_12h", Unit::Percent, Quantity::Percent),
+    "wwZh" => element("p_drizzle_12h", Unit::Percent, Quantity::Percent),
+    "R610" => element("p_precipitation_1mm_6h", Unit::Percent, Quantity::Percent),
+    "RRh" => element("precipitation_12h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RRhc" => element("precipitation_12h_significant_weather", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "ww3" => element("significant_weather_3h", Unit::Code, Quantity::Code),
+    "RRL1c" => element("liquid_precipitation_1h_significant_weather", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "Rd00" => element("p_precipitation_00_24h", Unit::Percent, Quantity::Percent),
+    "Rd10" => element("p_precipitation_1mm_24h", Unit::Percent, Quantity::Percent),
+    "RRd" => element("precipitation_24h", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "RRdc" => element("precipitation_24h_significant_weather", Unit::KilogramsPerSquareMeter, Quantity::Precipitation),
+    "Nlm" => element("cloud_cover_low_mid_7km", Unit::Percent, Quantity::Percent),
+    "wwPd" => element("p_precipitation_24h", Unit::Percent, Quantity::Percent),
+    "H_BsC" => element("cloud_base_convective_clouds", Unit::Meters, Quantity::Height),
+    "wwTd" => element("p_thunderstorms_24h", Unit::Percent, Quantity::Percent),
+    "E_PPP" => element("e_surface_pressure", Unit::Pascal, Quantity::Pressure),
+    "SunD3" => element("sunshine_duration_3h", Unit::Seconds, Quantity::Duration),
+    "WPc11" => element("opt_significant_weather_1h", Unit::Code, Quantity::Code),
+    "WPc31" => element("opt_significant_weather_3h", Unit::Code, Quantity::Code),
+    "WPc61" => element("opt_significant_weather_6h", Unit::Code, Quantity::Code),
+    "WPch1" => element("opt_significant_weather_12h", Unit::Code, Quantity::Code),
+    "WPcd1" => element("opt_significant_weather_24h", Unit::Code, Quantity::Code),
+    "Sa3" => element("accumulated_snow_3h", Unit::Meters, Quantity::SnowDepth),
+    "Sa6" => element("accumulated_snow_6h", Unit::Meters, Quantity::SnowDepth),
+    "Sah" => element("accumulated_snow_12h", Unit::Meters, Quantity::SnowDepth),
+    "Sad" => element("accumulated_snow_24h", Unit::Meters, Quantity::SnowDepth),
+    "Sa605" => element("p_snow_5cm_6h", Unit::Percent, Quantity::Percent),
+    "Sa610" => element("p_snow_10cm_6h", Unit::Percent, Quantity::Percent),
+    "Sa620" => element("p_snow_20cm_6h", Unit::Percent, Quantity::Percent),
+    "Sah05" => element("p_snow_5cm_12h", Unit::Percent, Quantity::Percent),
+    "Sah10" => element("p_snow_10cm_12h", Unit::Percent, Quantity::Percent),
+    "Sah30" => element("p_snow_30cm_12h", Unit::Percent, Quantity::Percent),
+    "Sad10" => element("p_snow_10cm_24h", Unit::Percent, Quantity::Percent),
+    "Sad30" => element("p_snow_30cm_24h", Unit::Percent, Quantity::Percent),
+    "Sad50" => element("p_snow_50cm_24h", Unit::Percent, Quantity::Percent),
+    "SnCv" => element("snow_depth", Unit::Meters, Quantity::SnowDepth),
+};
+
+/// Elements computed by this API (see [`crate::derived`]) by their key.
+pub static DERIVED_ELEMENTS: phf::Map<&'static str, ForecastElement> = phf::phf_map! {
+    "relative_humidity" => element("relative_humidity", Unit::Percent, Quantity::Percent),
+    "apparent_temperature" => element("apparent_temperature", Unit::Kelvin, Quantity::Temperature),
+    "wind_chill" => element("wind_chill", Unit::Kelvin, Quantity::Temperature),
+    "heat_index" => element("heat_index", Unit::Kelvin, Quantity::Temperature),
+    "beaufort" => element("beaufort", Unit::Beaufort, Quantity::Code),
+};
+
+lazy_static! {
+    static ref ELEMENTS_BY_KEY: HashMap<&'static str, &'static ForecastElement> = KML_ELEMENTS
+        .values()
+        .chain(DERIVED_ELEMENTS.values())
+        .map(|element| (element.key, element))
+        .collect();
+    static ref DWD_NAMES_BY_KEY: HashMap<&'static str, &'static str> = KML_ELEMENTS
+        .entries()
+        .map(|(name, element)| (element.key, *name))
+        .collect();
+}
+
+pub fn element_by_key(key: &str) -> Option<&'static ForecastElement> {
+    ELEMENTS_BY_KEY.get(key).copied()
+}
+
+/// The DWD name of an element, `None` for derived elements.
+pub fn dwd_name(key: &str) -> Option<&'static str> {
+    DWD_NAMES_BY_KEY.get(key).copied()
+}
+
+/// Every key an element can have in a forecast: the keys of this API, the DWD names and the derived elements.
+pub fn all_element_keys() -> impl Iterator<Item = &'static str> {
+    KML_ELEMENTS
+        .values()
+        .map(|element| element.key)
+        .chain(KML_ELEMENTS.keys().copied())
+        .chain(DERIVED_ELEMENTS.keys().copied())
+}
+
+/// Resolves a key or a DWD name (e.g. `temp` or `TTT`) to the key.
+pub fn resolve_key(name: &str) -> Option<&'static str> {
+    KML_ELEMENTS
+        .get(name)
+        .or_else(|| element_by_key(name))
+        .map(|element| element.key)
+}
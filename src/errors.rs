@@ -1,3 +1,8 @@
+use utoipa::{
+    openapi::{schema::Type, ObjectBuilder, RefOr, Schema},
+    PartialSchema, ToSchema,
+};
+
 #[derive(Debug, thiserror::Error, actix_web_error::Json)]
 pub enum DwdError {
     // weather report
@@ -16,6 +21,9 @@ pub enum DwdError {
     #[error("The report's CSV file contained an invalid row")]
     #[status(500)]
     BadCsvLine,
+    #[error("No report listing was found")]
+    #[status(404)]
+    NoReportListing,
 
     // weather forecast
     #[error("No forecast was found for this station")]
@@ -33,12 +41,60 @@ pub enum DwdError {
     #[error("Couldn't parse issue-time ({0})")]
     #[status(500)]
     InvalidIssueTime(chrono::ParseError),
+    #[error("Unknown element {0}")]
+    #[status(400)]
+    UnknownElement(String),
+
+    // map
+    #[error("Invalid time {0}, expected RFC 3339 or epoch milliseconds")]
+    #[status(400)]
+    InvalidTime(String),
+    #[error("The requested time isn't covered by the latest forecast run")]
+    #[status(404)]
+    TimeNotInRun,
+    #[error("No station has a value for this element")]
+    #[status(404)]
+    NoMapData,
+    #[error("Element {0} isn't available for all stations (MOSMIX_S)")]
+    #[status(400)]
+    ElementNotInProduct(String),
 
     // stations
     #[error("No station listing was found")]
     #[status(404)]
     NoStationListing,
 
+    // parsing
+    #[error("The upstream data didn't pass strict parsing ({0})")]
+    #[status(502)]
+    StrictParsing(crate::diagnostics::ParseWarning),
+
+    // batch
+    #[error("Too many stations, at most {0} are allowed")]
+    #[status(400)]
+    BatchTooLarge(usize),
+
+    // formats
+    #[error("This format isn't supported by this endpoint")]
+    #[status(406)]
+    UnsupportedFormat,
+    #[error("Unknown format ({0})")]
+    #[status(406)]
+    UnknownFormat(String),
+    #[error("Invalid CSV options ({0})")]
+    #[status(400)]
+    InvalidCsvOptions(String),
+
+    // images
+    #[error("Invalid image options ({0})")]
+    #[status(400)]
+    InvalidImageOptions(&'static str),
+
+    // schemas
+    #[error("Unknown schema {0}")]
+    #[status(404)]
+    UnknownSchema(String),
+
     // generic
     #[error("Internal error")]
     #[status(500)]
@@ -47,3 +103,20 @@ pub enum DwdError {
     #[status(404)]
     NoRoute,
 }
+
+/// Errors are responded with as `{ "error": "<message>" }`.
+impl PartialSchema for DwdError {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .property(
+                "error",
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .description(Some("The error message")),
+            )
+            .required("error")
+            .into()
+    }
+}
+
+impl ToSchema for DwdError {}
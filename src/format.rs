@@ -0,0 +1,282 @@
+use crate::{
+    arrow_export::{ARROW_STREAM, PARQUET},
+    geojson::{FeatureCollection, ToFeature, GEOJSON},
+    DwdError,
+};
+use actix_web::{
+    dev::Payload,
+    http::header::{self, Header},
+    web::{self, Bytes},
+    FromRequest, HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError,
+};
+use futures::{
+    future::{ready, Ready},
+    stream, Stream, StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const NDJSON: &str = "application/x-ndjson";
+pub const CSV: &str = "text/csv; charset=utf-8";
+pub const MSGPACK: &str = "application/msgpack";
+pub const CBOR: &str = "application/cbor";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Json,
+    /// One JSON document per line
+    #[serde(rename = "ndjson")]
+    NdJson,
+    Csv,
+    #[serde(rename = "geojson")]
+    GeoJson,
+    /// Arrow IPC stream
+    Arrow,
+    Parquet,
+    #[serde(rename = "msgpack")]
+    MsgPack,
+    Cbor,
+}
+
+impl ResponseFormat {
+    fn from_mime(mime: &str) -> Option<Self> {
+        match mime {
+            "application/json" | "application/*" | "*/*" => Some(ResponseFormat::Json),
+            NDJSON | "application/ndjson" => Some(ResponseFormat::NdJson),
+            "text/csv" => Some(ResponseFormat::Csv),
+            GEOJSON => Some(ResponseFormat::GeoJson),
+            ARROW_STREAM => Some(ResponseFormat::Arrow),
+            PARQUET => Some(ResponseFormat::Parquet),
+            MSGPACK | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(ResponseFormat::MsgPack)
+            }
+            CBOR => Some(ResponseFormat::Cbor),
+            _ => None,
+        }
+    }
+
+    /// JSON, MessagePack and CBOR can encode every response, so they're supported by every endpoint.
+    fn is_universal(self) -> bool {
+        matches!(
+            self,
+            ResponseFormat::Json | ResponseFormat::MsgPack | ResponseFormat::Cbor
+        )
+    }
+
+    /// Arrow and Parquet, which have typed timestamps (`time_format` doesn't apply).
+    pub fn is_columnar(self) -> bool {
+        matches!(self, ResponseFormat::Arrow | ResponseFormat::Parquet)
+    }
+
+    /// The content type and file extension.
+    fn file_type(self) -> (&'static str, &'static str) {
+        match self {
+            ResponseFormat::Json => ("application/json", "json"),
+            ResponseFormat::NdJson => (NDJSON, "ndjson"),
+            ResponseFormat::Csv => (CSV, "csv"),
+            ResponseFormat::GeoJson => (GEOJSON, "geojson"),
+            ResponseFormat::Arrow => (ARROW_STREAM, "arrow"),
+            ResponseFormat::Parquet => (PARQUET, "parquet"),
+            ResponseFormat::MsgPack => (MSGPACK, "msgpack"),
+            ResponseFormat::Cbor => (CBOR, "cbor"),
+        }
+    }
+
+    /// Responds with `body` as MessagePack, CBOR or JSON (for all other formats).
+    pub fn respond<T: Serialize>(
+        self,
+        mut response: HttpResponseBuilder,
+        body: &T,
+    ) -> HttpResponse {
+        let encoded = match self {
+            // with field names, so structs are maps like in JSON
+            ResponseFormat::MsgPack => rmp_serde::to_vec_named(body).ok(),
+            ResponseFormat::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(body, &mut out).ok().map(|_| out)
+            }
+            _ => return response.json(body),
+        };
+        match encoded {
+            Some(encoded) => response.content_type(self.file_type().0).body(encoded),
+            None => DwdError::InternalError.error_response(),
+        }
+    }
+
+    /// Responds with an array, one item per line or a GeoJSON `FeatureCollection`.
+    pub fn list<T: Serialize + ToFeature + 'static>(
+        self,
+        response: HttpResponseBuilder,
+        items: Vec<T>,
+    ) -> HttpResponse {
+        match self {
+            ResponseFormat::NdJson => ndjson_response(response, stream::iter(items)),
+            ResponseFormat::GeoJson => geojson_response(
+                response,
+                &items.into_iter().collect::<FeatureCollection<_>>(),
+            ),
+            _ => self.respond(response, &items),
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FormatQuery {
+    /// Takes precedence over the `Accept` header
+    format: Option<ResponseFormat>,
+}
+
+/// The formats a client asked for, either explicitly with `?format=` or with the `Accept` header.
+pub struct FormatRequest {
+    explicit: Option<ResponseFormat>,
+    /// From the `Accept` header, most preferred first
+    accepted: Vec<ResponseFormat>,
+}
+
+impl FormatRequest {
+    /// Picks the most preferred format the endpoint supports (JSON, MessagePack and CBOR are always supported).
+    /// Falls back to JSON if the `Accept` header doesn't contain a supported format,
+    /// but an unsupported `?format=` is an error.
+    pub fn select(&self, supported: &[ResponseFormat]) -> Result<ResponseFormat, DwdError> {
+        let is_supported =
+            |format: &ResponseFormat| format.is_universal() || supported.contains(format);
+        match self.explicit {
+            Some(format) if is_supported(&format) => Ok(format),
+            Some(_) => Err(DwdError::UnsupportedFormat),
+            None => Ok(self
+                .accepted
+                .iter()
+                .copied()
+                .find(is_supported)
+                .unwrap_or(ResponseFormat::Json)),
+        }
+    }
+}
+
+impl FromRequest for FormatRequest {
+    type Error = DwdError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let explicit = web::Query::<FormatQuery>::from_query(req.query_string())
+            .map(|q| q.into_inner().format)
+            .map_err(|e| DwdError::UnknownFormat(e.to_string()));
+        let accepted = header::Accept::parse(req)
+            .map(|accept| {
+                accept
+                    .ranked()
+                    .iter()
+                    .filter_map(|mime| ResponseFormat::from_mime(mime.essence_str()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        ready(explicit.map(|explicit| FormatRequest { explicit, accepted }))
+    }
+}
+
+/// Streams each item as one line of JSON as soon as it's ready.
+pub fn ndjson_response<S, T>(mut response: HttpResponseBuilder, items: S) -> HttpResponse
+where
+    S: Stream<Item = T> + 'static,
+    T: Serialize,
+{
+    response.content_type(NDJSON).streaming(items.map(|item| {
+        let mut line = serde_json::to_vec(&item)?;
+        line.push(b'\n');
+        Ok::<_, serde_json::Error>(Bytes::from(line))
+    }))
+}
+
+/// Responds with a file that's downloaded as `{name}.{extension}` (e.g. `10384.csv`).
+pub fn file_response(
+    mut response: HttpResponseBuilder,
+    format: ResponseFormat,
+    name: &str,
+    body: Vec<u8>,
+) -> HttpResponse {
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect();
+    let (content_type, extension) = format.file_type();
+    response
+        .content_type(content_type)
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{name}.{extension}\""),
+        ))
+        .body(body)
+}
+
+pub fn geojson_response<T: Serialize>(mut response: HttpResponseBuilder, body: &T) -> HttpResponse {
+    match serde_json::to_vec(body) {
+        Ok(body) => response.content_type(GEOJSON).body(body),
+        Err(_) => DwdError::InternalError.error_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test::TestRequest};
+
+    fn format_request(req: TestRequest) -> Result<FormatRequest, DwdError> {
+        FormatRequest::from_request(&req.to_http_request(), &mut Payload::None).into_inner()
+    }
+
+    #[test]
+    fn explicit_format_takes_precedence() {
+        let req = TestRequest::with_uri("/?format=csv").insert_header(("Accept", "text/csv"));
+        let format = format_request(req).unwrap();
+        assert_eq!(
+            format.select(&[ResponseFormat::Csv]).unwrap(),
+            ResponseFormat::Csv
+        );
+    }
+
+    #[test]
+    fn accept_header_falls_back_to_json() {
+        let req =
+            TestRequest::default().insert_header(("Accept", "text/csv, application/cbor;q=0.5"));
+        let format = format_request(req).unwrap();
+        assert_eq!(
+            format.select(&[ResponseFormat::Csv]).unwrap(),
+            ResponseFormat::Csv
+        );
+        assert_eq!(format.select(&[]).unwrap(), ResponseFormat::Cbor);
+
+        let req = TestRequest::default().insert_header(("Accept", "image/png"));
+        assert_eq!(
+            format_request(req).unwrap().select(&[]).unwrap(),
+            ResponseFormat::Json
+        );
+    }
+
+    #[test]
+    fn negotiation_failures_are_json_errors() {
+        let Err(unknown @ DwdError::UnknownFormat(_)) =
+            format_request(TestRequest::with_uri("/?format=xml"))
+        else {
+            panic!("expected an unknown format");
+        };
+        assert_eq!(
+            unknown.to_string(),
+            "Unknown format (Query deserialize error: unknown variant `xml`, expected one of `json`, `ndjson`, `csv`, `geojson`, `arrow`, `parquet`, `msgpack`, `cbor`)"
+        );
+
+        let unsupported = format_request(TestRequest::with_uri("/?format=csv"))
+            .unwrap()
+            .select(&[ResponseFormat::GeoJson])
+            .unwrap_err();
+        for error in [unknown, unsupported] {
+            let response = error.error_response();
+            assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+            assert_eq!(
+                response.headers().get(header::CONTENT_TYPE).unwrap(),
+                "application/json"
+            );
+        }
+    }
+}
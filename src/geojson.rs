@@ -0,0 +1,52 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub const GEOJSON: &str = "application/geo+json";
+
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "type")]
+pub struct FeatureCollection<P> {
+    pub features: Vec<Feature<P>>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "type")]
+pub struct Feature<P> {
+    pub geometry: Option<Point>,
+    pub properties: P,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "type")]
+pub struct Point {
+    /// `[longitude, latitude]` or `[longitude, latitude, elevation]`
+    pub coordinates: Vec<f64>,
+}
+
+impl Point {
+    pub fn new(longitude: f64, latitude: f64, elevation: Option<f64>) -> Self {
+        Self {
+            coordinates: [longitude, latitude].into_iter().chain(elevation).collect(),
+        }
+    }
+}
+
+/// Something that can be placed on a map.
+pub trait ToFeature: Sized {
+    fn geometry(&self) -> Option<Point>;
+
+    fn into_feature(self) -> Feature<Self> {
+        Feature {
+            geometry: self.geometry(),
+            properties: self,
+        }
+    }
+}
+
+impl<P: ToFeature> FromIterator<P> for FeatureCollection<P> {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        Self {
+            features: iter.into_iter().map(ToFeature::into_feature).collect(),
+        }
+    }
+}
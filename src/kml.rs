@@ -1,11 +1,22 @@
 use crate::{
+    diagnostics::{Diagnostics, ParseOptions, ParseWarning},
+    elements::KML_ELEMENTS,
     errors::DwdError,
-    weather_forecast::{Forecast, ForecastReferenceModel},
+    time_format::Timestamp,
+    units::Unit,
+    weather_forecast::{Coordinates, Forecast, ForecastReferenceModel},
 };
 use chrono::DateTime;
-use serde::Deserialize;
+use serde::{
+    de::{DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
 use serde_json::Value;
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    str::FromStr,
+};
 
 #[derive(Deserialize, Debug)]
 struct Kml {
@@ -94,209 +105,496 @@ struct ProductDefinition {
     //     format_config: { DefaultUndefSign: String },
 }
 
-type KmlForecastData = (HashMap<&'static str, Vec<Value>>, usize);
+struct KmlForecastData {
+    data: HashMap<&'static str, Vec<Value>>,
+    units: BTreeMap<&'static str, Unit>,
+    raw: HashMap<String, Vec<Value>>,
+    n_time_steps: usize,
+}
 
-pub fn deserialize_to_forecast<R: std::io::Read>(raw: R) -> Result<Forecast, DwdError> {
+pub fn deserialize_to_forecast<R: std::io::Read>(
+    raw: R,
+    options: ParseOptions,
+) -> Result<Forecast, DwdError> {
     let deserialized: Kml = serde_xml_rs::from_reader(raw).map_err(DwdError::InvalidKml)?;
     let product_def = deserialized.document.extended_data.product_definition;
 
-    let (data, n_data_points) = kml_to_forecast_data(
+    let mut diagnostics = Diagnostics::new(options.mode);
+    let forecast_data = kml_to_forecast_data(
         &deserialized.document.placemark.extended_data.forecasts,
         &product_def.forecast_time_steps.time_steps,
-    );
+        options.passthrough,
+        &mut diagnostics,
+    )?;
+
+    let coordinates = deserialized.document.placemark.point.coordinates;
+    let location = parse_coordinates(&coordinates);
+    if location.is_none() {
+        diagnostics.warn(ParseWarning::InvalidCoordinates {
+            coordinates: coordinates.clone(),
+        })?;
+    }
+
+    let mut reference_models = Vec::new();
+    for model in &product_def.referenced_models.models {
+        match parse_time(&model.reference_time) {
+            Some(reference_time) => reference_models.push(ForecastReferenceModel {
+                name: model.name.clone(),
+                reference_time: Timestamp::from_millis(reference_time),
+            }),
+            None => diagnostics.warn(ParseWarning::InvalidTimestamp {
+                timestamp: model.reference_time.clone(),
+            })?,
+        }
+    }
 
     Ok(Forecast {
         issuer: product_def.issuer,
         name: deserialized.document.placemark.name,
-        coordinates: deserialized.document.placemark.point.coordinates,
+        coordinates,
+        location,
         description: deserialized.document.placemark.description,
         generating_process: product_def.generating_process,
-        issue_time: DateTime::parse_from_rfc3339(&product_def.issue_time)
-            .map_err(DwdError::InvalidIssueTime)?
-            .timestamp_millis() as u64,
-        reference_models: product_def
-            .referenced_models
-            .models
-            .iter()
-            .map(|m| ForecastReferenceModel {
-                name: m.name.clone(),
-                reference_time: DateTime::parse_from_rfc3339(&m.reference_time.clone())
-                    .map(|d| d.timestamp_millis())
-                    .unwrap_or(0) as u64,
-            })
-            .collect(),
-        data,
-        n_data_points,
+        issue_time: Timestamp::from_millis(
+            DateTime::parse_from_rfc3339(&product_def.issue_time)
+                .map_err(DwdError::InvalidIssueTime)?
+                .timestamp_millis(),
+        ),
+        reference_models,
+        data: forecast_data.data,
+        units: forecast_data.units,
+        n_data_points: forecast_data.n_time_steps,
+        weather: None,
+        raw: options.passthrough.then_some(forecast_data.raw),
+        warnings: diagnostics.into_warnings(),
     })
 }
 
-fn kml_to_forecast_data(forecasts: &[DwdForecast], time_steps: &[String]) -> KmlForecastData {
-    let mut json = HashMap::<&'static str, Vec<Value>>::new();
-    let time_steps: Vec<Value> = time_steps
+/// The all-stations product, which has one placemark per station.
+pub struct AllStations {
+    pub issue_time: i64,
+    pub time_steps: Vec<i64>,
+    pub stations: Vec<RunStation>,
+    /// The keys of the known elements in the product
+    pub elements: BTreeSet<&'static str>,
+    /// The values of the requested element for each station (`None` if a station doesn't have it)
+    pub values: Vec<Option<Vec<f32>>>,
+}
+
+/// A station of the all-stations product.
+pub struct RunStation {
+    pub id: String,
+    pub name: String,
+    pub location: Coordinates,
+}
+
+/// Parses the all-stations product and the values of one element (by key), if it's given.
+/// The placemarks are processed one after another, so only the requested values are kept in memory.
+/// This is always lenient: stations with invalid coordinates and time steps that can't be parsed are
+/// skipped and values that aren't a number (or an element with the wrong number of values) are missing.
+pub fn deserialize_all_stations<R: std::io::Read>(
+    raw: R,
+    element: Option<&'static str>,
+) -> Result<AllStations, DwdError> {
+    let mut deserializer = serde_xml_rs::Deserializer::new_from_reader(raw);
+    let (product_def, placemarks) = AllStationsSeed { element }
+        .deserialize(&mut deserializer)
+        .map_err(DwdError::InvalidKml)?;
+
+    let time_steps: Vec<Option<i64>> = product_def
+        .forecast_time_steps
+        .time_steps
         .iter()
-        .map(|s| {
-            Value::from(
-                DateTime::parse_from_rfc3339(s)
-                    .map(|d| d.timestamp_millis())
-                    .unwrap_or(0),
+        .map(|time_step| parse_time(time_step))
+        .collect();
+    let values = placemarks
+        .values
+        .into_iter()
+        .map(|values| {
+            let values = values.filter(|values| values.len() == time_steps.len())?;
+            Some(
+                values
+                    .into_iter()
+                    .zip(&time_steps)
+                    .filter_map(|(value, time_step)| time_step.map(|_| value))
+                    .collect(),
             )
         })
         .collect();
-    let n_time_steps = time_steps.len();
-    json.insert("time_steps", time_steps);
+
+    Ok(AllStations {
+        issue_time: DateTime::parse_from_rfc3339(&product_def.issue_time)
+            .map_err(DwdError::InvalidIssueTime)?
+            .timestamp_millis(),
+        time_steps: time_steps.into_iter().flatten().collect(),
+        stations: placemarks.stations,
+        elements: placemarks.elements,
+        values,
+    })
+}
+
+/// The element (key) whose values are kept, threaded through the `kml` and `Document` elements.
+#[derive(Clone, Copy)]
+struct AllStationsSeed {
+    element: Option<&'static str>,
+}
+
+#[derive(Default)]
+struct Placemarks {
+    stations: Vec<RunStation>,
+    elements: BTreeSet<&'static str>,
+    values: Vec<Option<Vec<f32>>>,
+}
+
+/// `<Document>` of the all-stations product.
+struct DocumentSeed(AllStationsSeed);
+
+/// The `<Placemark>`s of the all-stations product.
+struct PlacemarksSeed<'a>(AllStationsSeed, &'a mut Placemarks);
+
+impl<'de> DeserializeSeed<'de> for AllStationsSeed {
+    type Value = (ProductDefinition, Placemarks);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("kml", &["Document"], self)
+    }
+}
+
+impl<'de> Visitor<'de> for AllStationsSeed {
+    type Value = (ProductDefinition, Placemarks);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a KML document")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut document = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "Document" => document = Some(map.next_value_seed(DocumentSeed(self))?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        document.ok_or_else(|| serde::de::Error::missing_field("Document"))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for DocumentSeed {
+    type Value = (ProductDefinition, Placemarks);
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("Document", &["ExtendedData", "Placemark"], self)
+    }
+}
+
+impl<'de> Visitor<'de> for DocumentSeed {
+    type Value = (ProductDefinition, Placemarks);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a KML Document")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut extended_data = None;
+        let mut placemarks = Placemarks::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "ExtendedData" => extended_data = Some(map.next_value::<KmlExtDocument>()?),
+                "Placemark" => map.next_value_seed(PlacemarksSeed(self.0, &mut placemarks))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let extended_data =
+            extended_data.ok_or_else(|| serde::de::Error::missing_field("ExtendedData"))?;
+        Ok((extended_data.product_definition, placemarks))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for PlacemarksSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for PlacemarksSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("KML Placemarks")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let PlacemarksSeed(AllStationsSeed { element }, placemarks) = self;
+        while let Some(placemark) = seq.next_element::<KmlPlacemark>()? {
+            let Some(location) = parse_coordinates(&placemark.point.coordinates) else {
+                continue;
+            };
+            let mut values = None;
+            for forecast in &placemark.extended_data.forecasts {
+                let Some(known) = KML_ELEMENTS.get(forecast.element_name.as_str()) else {
+                    continue;
+                };
+                placemarks.elements.insert(known.key);
+                if Some(known.key) == element {
+                    values = Some(
+                        forecast
+                            .value
+                            .split_whitespace()
+                            .map(|s| f32::from_str(s).unwrap_or(f32::NAN))
+                            .collect(),
+                    );
+                }
+            }
+            placemarks.stations.push(RunStation {
+                id: placemark.name,
+                name: placemark.description,
+                location,
+            });
+            placemarks.values.push(values);
+        }
+        Ok(())
+    }
+}
+
+/// Parses a time step or reference time as epoch milliseconds.
+fn parse_time(raw: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|time| time.timestamp_millis())
+}
+
+/// Parses KML coordinates (`longitude,latitude[,altitude]`).
+fn parse_coordinates(raw: &str) -> Option<Coordinates> {
+    let mut parts = raw.trim().split(',').map(|p| f64::from_str(p.trim()));
+
+    let longitude = parts.next()?.ok()?;
+    let latitude = parts.next()?.ok()?;
+    let elevation = match parts.next() {
+        Some(elevation) => Some(elevation.ok()?),
+        None => None,
+    };
+    if parts.next().is_some()
+        || !(-180.0..=180.0).contains(&longitude)
+        || !(-90.0..=90.0).contains(&latitude)
+        || elevation.is_some_and(|e| !e.is_finite())
+    {
+        return None;
+    }
+
+    Some(Coordinates {
+        longitude,
+        latitude,
+        elevation,
+    })
+}
+
+fn kml_to_forecast_data(
+    forecasts: &[DwdForecast],
+    time_steps: &[String],
+    passthrough: bool,
+    diagnostics: &mut Diagnostics,
+) -> Result<KmlForecastData, DwdError> {
+    let mut json = HashMap::<&'static str, Vec<Value>>::new();
+    let mut units = BTreeMap::<&'static str, Unit>::new();
+    let mut raw = HashMap::<String, Vec<Value>>::new();
+    // time steps that can't be parsed are skipped with their values
+    let mut parsed_time_steps = Vec::new();
+    let mut valid = Vec::with_capacity(time_steps.len());
+    for time_step in time_steps {
+        let parsed = parse_time(time_step);
+        match parsed {
+            Some(millis) => parsed_time_steps.push(Value::from(millis)),
+            None => diagnostics.warn(ParseWarning::InvalidTimestamp {
+                timestamp: time_step.clone(),
+            })?,
+        }
+        valid.push(parsed.is_some());
+    }
+    let n_time_steps = parsed_time_steps.len();
+    json.insert("time_steps", parsed_time_steps);
 
     for forecast in forecasts {
+        let element = KML_ELEMENTS.get(forecast.element_name.as_str());
+        if element.is_none() && !passthrough {
+            diagnostics.warn(ParseWarning::UnknownElement {
+                element: forecast.element_name.clone(),
+            })?;
+            continue;
+        }
         let values: Vec<Value> = forecast
             .value
             .split_whitespace()
             .map(|s| f64::from_str(s).map(Value::from).unwrap_or(Value::Null))
             .collect();
-        if values.len() != n_time_steps {
+        if values.len() != valid.len() {
+            diagnostics.warn(ParseWarning::LengthMismatch {
+                element: forecast.element_name.clone(),
+                expected: valid.len(),
+                actual: values.len(),
+            })?;
             continue;
         }
-        if let Some(key) = KML_ELEMENT_TO_JSON_KEY.get(forecast.element_name.as_str()) {
-            json.insert(key, values);
+        let values = values
+            .into_iter()
+            .zip(&valid)
+            .filter_map(|(value, &valid)| valid.then_some(value))
+            .collect();
+        match element {
+            Some(element) => {
+                json.insert(element.key, values);
+                units.insert(element.key, element.unit);
+            }
+            None => {
+                raw.insert(forecast.element_name.clone(), values);
+            }
         }
     }
 
-    (json, n_time_steps)
+    Ok(KmlForecastData {
+        data: json,
+        units,
+        raw,
+        n_time_steps,
+    })
 }
 
-static KML_ELEMENT_TO_JSON_KEY: phf::Map<&'static str, &'static str> = phf::phf_map! {
-    "TTT" => "temp",
-    "Td" => "dew_point",
-    "TX" => "max_temp",
-    "TN" => "min_temp",
-    "DD" => "wind_direction",
-    "FF" => "wind_speed",
-    "FX1" => "max_wind_gust_1h",
-    "FX3" => "max_wind_gust_3h",
-    "FXh" => "max_wind_gust_12h",
-    "RR1c" => "precipitation_1h_significant_weather",
-    "RR1" => "precipitation_1h",
-    "RR3c" => "precipitation_3h_significant_weather",
-    "RR3" => "precipitation_3h",
-    "RRS1c" => "snow_rain_eq_1h",
-    "RRS3c" => "snow_rain_eq_3h",
-    "ww" => "significant_weather",
-    "W1W2" => "past_weather_6h",
-    "N" => "total_cloud_cover",
-    "Neff" => "effective_cloud_cover",
-    "N05" => "cloud_cover_500ft",
-    "Nl" => "low_cloud_cover",
-    "Nm" => "midlevel_cloud_cover",
-    "Nh" => "high_cloud_cover",
-    "PPPP" => "surface_pressure",
-    "T5cm" => "temp_5cm",
-    "RadS3" => "shortwave_radiation_3h",
-    "Rad1h" => "global_irradiance",
-    "RadL3" => "longwave_radiation_3h",
-    "VV" => "visibility",
-    "SunD1" => "sunshine_last_hour",
-    "FXh25" => "p_wind_gust_25kn_12h",
-    "FXh40" => "p_wind_gust_40kn_12h",
-    "FXh55" => "p_wind_gust_55kn_12h",
-    "wwM" => "p_fog_1h",
-    "wwM6" => "p_fog_6h",
-    "wwMh" => "p_fog_12h",
-    "Rh00" => "p_precipitation_0mm_12h",
-    "R602" => "p_precipitation_p2mm_6h",
-    "Rh02" => "p_precipitation_p2mm_12h",
-    "Rd02" => "p_precipitation_p2mm_24h",
-    "Rh10" => "p_precipitation_1mm_12h",
-    "R650" => "p_precipitation_5mm_6h",
-    "Rh50" => "p_precipitation_5mm_12h",
-    "Rd50" => "p_precipitation_5mm_24h",
-    "TG" => "min_temp_5cm_12h",
-    "TM" => "mean_temp_24h",
-    "DRR1" => "precipitation_duration_1h",
-    "wwZ" => "p_drizzle_1h",
-    "wwD" => "p_straitform_precipitation_1h",
-    "wwC" => "p_convective_precipitation_1h",
-    "wwT" => "p_thunderstorms_1h",
-    "wwL" => "p_liquid_precipitation_1h",
-    "wwS" => "p_solid_precipitation_1h",
-    "wwF" => "p_freezing_rain_1h",
-    "wwP" => "p_precipitation_1h",
-    "VV10" => "p_visibility_below_1km",
-    "E_TTT" => "e_temp",
-    "E_FF" => "e_wind_speed",
-    "E_DD" => "e_wind_direction",
-    "E_Td" => "e_dew_point",
-    "RR6" => "precipitation_6h",
-    "RR6c" => "precipitation_6h_significant_weather",
-    "R600" => "p_precipitation_0mm_6h",
-    "R101" => "p_precipitation_p1mm_1h",
-    "R102" => "p_precipitation_p2mm_1h",
-    "R103" => "p_precipitation_p3mm_1h",
-    "R105" => "p_precipitation_p5mm_1h",
-    "R107" => "p_precipitation_p7mm_1h",
-    "R110" => "p_precipitation_1mm_1h",
-    "R120" => "p_precipitation_2mm_1h",
-    "SunD" => "sunshine_duration_yesterday",
-    "RSunD" => "rel_sunshine_duration_24h",
-    "PSd00" => "p_rel_sunshine_duration_24h",
-    "PSd30" => "p_rel_sunshine_duration_30p_24h",
-    "PSd60" => "p_rel_sunshine_duration_60p_24h",
-    "RRad1" => "global_irradiance_1h",
-    "PEvap" => "potential_evapotranspiration_24h",
-    "R130" => "p_precipitation_3mm_1h",
-    "R150" => "p_precipitation_5mm_1h",
-    "RR1o1" => "p_precipitation_10mm_1h",
-    "RR1w1" => "p_precipitation_15mm_1h",
-    "RR1u1" => "p_precipitation_25mm_1h",
-    "wwD6" => "p_straightform_precipitation_6h",
-    "wwC6" => "p_convective_precipitation_6h",
-    "wwT6" => "p_thunderstorms_6h",
-    "wwP6" => "p_precipitation_6h",
-    "wwL6" => "p_liquid_precipitation_6h",
-    "wwF6" => "p_freezing_rain_6h",
-    "wwS6" => "p_solid_precipitation_6h",
-    "wwZ6" => "p_drizzle_6h",
-    "wwMd" => "p_fog_24h",
-    "FX625" => "p_gusts_25kn_6h",
-    "FX640" => "p_gusts_40kn_6h",
-    "FX655" => "p_gusts_55kn_6h",
-    "wwDh" => "p_straightform_precipitation_12h",
-    "wwCh" => "p_convective_precipitation_12h",
-    "wwTh" => "p_thunderstorms_12h",
-    "wwPh" => "p_precipitation_12h",
-    "wwLh" => "p_liquid_precipitation_12h",
-    "wwFh" => "p_freezing_rain_12h",
-    "wwSh" => "p_solid_precipitation_12h",
-    "wwZh" => "p_drizzle_12h",
-    "R610" => "p_precipitation_1mm_6h",
-    "RRh" => "precipitation_12h",
-    "RRhc" => "precipitation_12h_significant_weather",
-    "ww3" => "significant_weather_3h",
-    "RRL1c" => "liquid_precipitation_1h_significant_weather",
-    "Rd00" => "p_precipitation_00_24h",
-    "Rd10" => "p_precipitation_1mm_24h",
-    "RRd" => "precipitation_24h",
-    "RRdc" => "precipitation_24h_significant_weather",
-    "Nlm" => "cloud_cover_low_mid_7km",
-    "wwPd" => "p_precipitation_24h",
-    "H_BsC" => "cloud_base_convective_clouds",
-    "wwTd" => "p_thunderstorms_24h",
-    "E_PPP" => "e_surface_pressure",
-    "SunD3" => "sunshine_duration_3h",
-    "WPc11" => "opt_significant_weather_1h",
-    "WPc31" => "opt_significant_weather_3h",
-    "WPc61" => "opt_significant_weather_6h",
-    "WPch1" => "opt_significant_weather_12h",
-    "WPcd1" => "opt_significant_weather_24h",
-    "Sa3" => "accumulated_snow_3h",
-    "Sa6" => "accumulated_snow_6h",
-    "Sah" => "accumulated_snow_12h",
-    "Sad" => "accumulated_snow_24h",
-    "Sa605" => "p_snow_5cm_6h",
-    "Sa610" => "p_snow_10cm_6h",
-    "Sa620" => "p_snow_20cm_6h",
-    "Sah05" => "p_snow_5cm_12h",
-    "Sah10" => "p_snow_10cm_12h",
-    "Sah30" => "p_snow_30cm_12h",
-    "Sad10" => "p_snow_10cm_24h",
-    "Sad30" => "p_snow_30cm_24h",
-    "Sad50" => "p_snow_50cm_24h",
-    "SnCv" => "snow_depth"
-};
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ParseMode;
+
+    fn kml(coordinates: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="ISO-8859-1" standalone="yes"?>
+<kml><Document><ExtendedData><ProductDefinition><Issuer>Deutscher Wetterdienst</Issuer><GeneratingProcess>DWD MOSMIX hourly, Version 1.0</GeneratingProcess><IssueTime>2024-06-10T03:00:00.000Z</IssueTime><ReferencedModel><Model><name>ICON</name><referenceTime>2024-06-09T18:00:00Z</referenceTime></Model></ReferencedModel><ForecastTimeSteps><TimeStep>2024-06-10T04:00:00.000Z</TimeStep><TimeStep>2024-06-10T05:00:00.000Z</TimeStep></ForecastTimeSteps></ProductDefinition></ExtendedData>
+<Placemark><name>10865</name><description>MUENCHEN STADT</description><ExtendedData><Forecast><elementName>TTT</elementName><value>288.15 289.15</value></Forecast></ExtendedData><Point><coordinates>{coordinates}</coordinates></Point></Placemark></Document></kml>"#
+        )
+    }
+
+    fn parse(coordinates: &str, mode: ParseMode) -> Result<Forecast, DwdError> {
+        let options = ParseOptions {
+            mode,
+            passthrough: false,
+        };
+        deserialize_to_forecast(kml(coordinates).as_bytes(), options)
+    }
+
+    #[test]
+    fn valid_coordinates() {
+        let forecast = parse("11.55,48.15,521.0", ParseMode::Strict).unwrap();
+        let location = forecast.location.unwrap();
+        assert_eq!(
+            (location.longitude, location.latitude, location.elevation),
+            (11.55, 48.15, Some(521.0))
+        );
+        assert_eq!(forecast.data["temp"].len(), 2);
+    }
+
+    #[test]
+    fn invalid_coordinates_are_a_warning() {
+        for coordinates in ["", "11.55", "11.55,north", "200.0,48.15", "1,2,3,4"] {
+            let forecast = parse(coordinates, ParseMode::Lenient).unwrap();
+            assert!(forecast.location.is_none());
+            assert_eq!(
+                forecast.warnings,
+                [ParseWarning::InvalidCoordinates {
+                    coordinates: coordinates.to_owned()
+                }]
+            );
+        }
+    }
+
+    const ALL_STATIONS: &str = r#"<?xml version="1.0" encoding="ISO-8859-1" standalone="yes"?>
+<kml><Document><ExtendedData><ProductDefinition><Issuer>Deutscher Wetterdienst</Issuer><GeneratingProcess>DWD MOSMIX hourly, Version 1.0</GeneratingProcess><IssueTime>2024-06-10T03:00:00.000Z</IssueTime><ReferencedModel><Model><name>ICON</name><referenceTime>2024-06-09T18:00:00Z</referenceTime></Model></ReferencedModel><ForecastTimeSteps><TimeStep>2024-06-10T04:00:00.000Z</TimeStep><TimeStep>2024-06-10T05:00:00.000Z</TimeStep></ForecastTimeSteps></ProductDefinition></ExtendedData>
+<Placemark><name>10865</name><description>MUENCHEN STADT</description><ExtendedData><Forecast><elementName>TTT</elementName><value>288.15 -</value></Forecast><Forecast><elementName>FF</elementName><value>2.5 3.0</value></Forecast></ExtendedData><Point><coordinates>11.55,48.15,521.0</coordinates></Point></Placemark>
+<Placemark><name>BROKEN</name><description>NOWHERE</description><ExtendedData><Forecast><elementName>TTT</elementName><value>280.0 281.0</value></Forecast></ExtendedData><Point><coordinates></coordinates></Point></Placemark>
+<Placemark><name>10384</name><description>BERLIN-TEMPELHOF</description><ExtendedData><Forecast><elementName>TTT</elementName><value>290.15</value></Forecast><Forecast><elementName>XYZ</elementName><value>1 2</value></Forecast></ExtendedData><Point><coordinates>13.4,52.47,48.0</coordinates></Point></Placemark>
+</Document></kml>"#;
+
+    #[test]
+    fn all_stations_with_one_element() {
+        let parsed = deserialize_all_stations(ALL_STATIONS.as_bytes(), Some("temp")).unwrap();
+
+        let ids: Vec<&str> = parsed.stations.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["10865", "10384"]);
+        assert_eq!(parsed.time_steps.len(), 2);
+        assert_eq!(parsed.elements, BTreeSet::from(["temp", "wind_speed"]));
+        let [munich, berlin] = parsed.values.as_slice() else {
+            panic!("expected one value per station");
+        };
+        let munich = munich.as_ref().unwrap();
+        assert_eq!(munich[0], 288.15);
+        assert!(munich[1].is_nan());
+        // the wrong number of values
+        assert_eq!(berlin, &None);
+    }
+
+    #[test]
+    fn all_stations_without_an_element() {
+        let parsed = deserialize_all_stations(ALL_STATIONS.as_bytes(), None).unwrap();
+
+        assert_eq!(parsed.stations.len(), 2);
+        assert_eq!(parsed.values, [None, None]);
+    }
+
+    #[test]
+    fn invalid_timestamps_are_skipped() {
+        let kml = kml("11.55,48.15,521.0")
+            .replace("2024-06-10T04:00:00.000Z", "tomorrow")
+            .replace("2024-06-09T18:00:00Z", "");
+        let options = ParseOptions::default();
+        let forecast = deserialize_to_forecast(kml.as_bytes(), options).unwrap();
+
+        assert_eq!(forecast.data["time_steps"], [Value::from(1717995600000i64)]);
+        assert_eq!(forecast.data["temp"], [Value::from(289.15)]);
+        assert_eq!(forecast.n_data_points, 1);
+        assert!(forecast.reference_models.is_empty());
+        assert_eq!(
+            forecast.warnings,
+            [
+                ParseWarning::InvalidTimestamp {
+                    timestamp: "tomorrow".to_owned()
+                },
+                ParseWarning::InvalidTimestamp {
+                    timestamp: String::new()
+                }
+            ]
+        );
+
+        let options = ParseOptions {
+            mode: ParseMode::Strict,
+            passthrough: false,
+        };
+        assert!(matches!(
+            deserialize_to_forecast(kml.as_bytes(), options),
+            Err(DwdError::StrictParsing(
+                ParseWarning::InvalidTimestamp { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn all_stations_skip_invalid_time_steps() {
+        let kml = ALL_STATIONS.replace("2024-06-10T04:00:00.000Z", "tomorrow");
+        let parsed = deserialize_all_stations(kml.as_bytes(), Some("wind_speed")).unwrap();
+
+        assert_eq!(parsed.time_steps, [1717995600000]);
+        assert_eq!(parsed.values[0], Some(vec![3.0]));
+    }
+
+    #[test]
+    fn invalid_coordinates_fail_strict_parsing() {
+        assert!(matches!(
+            parse("", ParseMode::Strict),
+            Err(DwdError::StrictParsing(
+                ParseWarning::InvalidCoordinates { .. }
+            ))
+        ));
+    }
+}
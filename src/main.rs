@@ -1,36 +1,532 @@
+mod arrow_export;
+mod batch;
+mod calendar;
+mod csv_export;
+mod current;
+mod daily;
+mod derived;
+mod diagnostics;
+mod elements;
 mod errors;
+mod format;
+mod geojson;
 mod kml;
+mod map;
+mod map_image;
+mod meteogram;
 mod mosmix_cfg;
+mod openapi;
+mod params;
+mod query;
+mod raster;
+mod report_stations;
+mod schema;
+mod time_format;
+mod timeline;
+mod units;
+mod weather_code;
 mod weather_forecast;
 mod weather_report;
 
 use crate::{
-    mosmix_cfg::get_mosmix_stations, weather_forecast::get_forecast,
-    weather_report::get_weather_report,
+    arrow_export::{batch_to_record_batch, encode_record_batch, table_to_record_batch, Table},
+    batch::{run_batch, stream_batch, BatchItem, BatchRequest},
+    calendar::{daily_to_ics, ICALENDAR},
+    csv_export::{forecast_to_csv, report_to_csv, CsvQuery},
+    current::{get_current, CurrentConditions},
+    daily::{aggregate_daily, decode_daily_weather, DailyForecast, DEFAULT_TIMEZONE},
+    derived::DerivedQuery,
+    diagnostics::ParseQuery,
+    elements::ElementsQuery,
+    format::{
+        file_response, geojson_response, ndjson_response, FormatQuery, FormatRequest,
+        ResponseFormat,
+    },
+    geojson::{Feature, FeatureCollection, ToFeature},
+    map::{get_map, MapQuery, MapSnapshot},
+    map_image::{render_map, MapImageQuery},
+    meteogram::{render_meteogram, MeteogramQuery},
+    mosmix_cfg::{get_mosmix_stations, MosmixStation},
+    openapi::{OPENAPI_JSON, REDOC_HTML},
+    params::{load_forecast, load_report, ForecastParams, ReportParams},
+    raster::{image_response, ImageFormat},
+    report_stations::{get_report_stations, ReportStation},
+    schema::{schema_index, SCHEMAS, SCHEMA_JSON},
+    time_format::{TimeFormat, TimeQuery},
+    timeline::{get_timeline, Timeline},
+    units::UnitsQuery,
+    weather_code::WeatherCodeQuery,
+    weather_forecast::{get_forecast, Forecast},
+    weather_report::WeatherReport,
+};
+use actix_web::{
+    get, http::header, middleware, post, web, App, HttpRequest, HttpResponse, HttpServer,
 };
-use actix_web::{get, http::header, middleware, web, App, HttpResponse, HttpServer};
 use errors::DwdError;
+use futures::stream;
+use std::rc::Rc;
 
+#[utoipa::path(
+    tag = "forecast",
+    params(("station" = String, Path, description = "The station's ID (e.g. `10865`)"), FormatQuery, WeatherCodeQuery, DerivedQuery, TimeQuery, UnitsQuery, ParseQuery, ElementsQuery, CsvQuery),
+    responses(
+        (status = 200, description = "The latest MOSMIX_L forecast", body = Forecast, content(
+            (Forecast = "application/json"),
+            (Feature<Forecast> = "application/geo+json"),
+            (String = "text/csv"),
+        )),
+        (status = 404, description = "No forecast was found", body = DwdError),
+        (status = 502, description = "The upstream data didn't pass strict parsing", body = DwdError),
+        (status = 406, description = "The requested format isn't supported", body = DwdError),
+    )
+)]
 #[get("/forecast/{station}")]
-async fn handle_station(station: web::Path<String>) -> Result<HttpResponse, DwdError> {
-    let forecast = get_forecast(&station).await?;
+async fn handle_station(
+    station: web::Path<String>,
+    mut params: ForecastParams,
+    format: FormatRequest,
+    req: HttpRequest,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[
+        ResponseFormat::Json,
+        ResponseFormat::Csv,
+        ResponseFormat::GeoJson,
+        ResponseFormat::Arrow,
+        ResponseFormat::Parquet,
+    ])?;
+    if format.is_columnar() {
+        params.time.time_format = TimeFormat::EpochMs;
+    }
+    let forecast = load_forecast(&station, &params).await?;
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=1000"));
+    Ok(match format {
+        ResponseFormat::Csv => file_response(
+            response,
+            format,
+            &station,
+            forecast_to_csv(&forecast, &CsvQuery::from_query(req.query_string())?)?,
+        ),
+        ResponseFormat::GeoJson => geojson_response(response, &forecast.into_feature()),
+        ResponseFormat::Arrow | ResponseFormat::Parquet => {
+            let batch = table_to_record_batch(Table::from_forecast(&forecast), params.time.tz)?;
+            file_response(
+                response,
+                format,
+                &station,
+                encode_record_batch(&batch, format)?,
+            )
+        }
+        _ => format.respond(response, &forecast),
+    })
+}
+
+#[utoipa::path(
+    tag = "forecast",
+    params(FormatQuery, WeatherCodeQuery, DerivedQuery, TimeQuery, UnitsQuery, ParseQuery, ElementsQuery),
+    responses(
+        (status = 200, description = "The forecast or error of each station", body = [BatchItem<Forecast>]),
+        (status = 400, description = "Too many stations", body = DwdError),
+        (status = 406, description = "The requested format isn't supported", body = DwdError),
+    )
+)]
+#[post("/forecast/batch")]
+async fn handle_forecast_batch(
+    body: web::Json<BatchRequest>,
+    format: FormatRequest,
+    mut params: ForecastParams,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[
+        ResponseFormat::Json,
+        ResponseFormat::NdJson,
+        ResponseFormat::Arrow,
+        ResponseFormat::Parquet,
+    ])?;
+    let stations = body.into_inner().stations()?;
+    if format.is_columnar() {
+        params.time.time_format = TimeFormat::EpochMs;
+    }
+    let tz = params.time.tz;
+    let params = Rc::new(params);
+    let load = move |station: String| {
+        let params = params.clone();
+        async move { load_forecast(&station, &params).await }
+    };
+    Ok(match format {
+        ResponseFormat::NdJson => ndjson_response(HttpResponse::Ok(), stream_batch(stations, load)),
+        ResponseFormat::Arrow | ResponseFormat::Parquet => {
+            let items = run_batch(stations, load).await;
+            let batch = batch_to_record_batch(items, Table::from_forecast, tz)?;
+            file_response(
+                HttpResponse::Ok(),
+                format,
+                "forecasts",
+                encode_record_batch(&batch, format)?,
+            )
+        }
+        _ => format.respond(HttpResponse::Ok(), &run_batch(stations, load).await),
+    })
+}
+
+#[utoipa::path(
+    tag = "forecast",
+    params(("station" = String, Path, description = "The station's ID (e.g. `10865`)"), FormatQuery, TimeQuery, WeatherCodeQuery, ParseQuery),
+    responses(
+        (status = 200, description = "The forecast aggregated per local day", body = DailyForecast),
+        (status = 404, description = "No forecast was found", body = DwdError),
+        (status = 502, description = "The upstream data didn't pass strict parsing", body = DwdError),
+    )
+)]
+#[get("/forecast/{station}/daily")]
+async fn handle_daily(
+    station: web::Path<String>,
+    time: web::Query<TimeQuery>,
+    weather_code: web::Query<WeatherCodeQuery>,
+    parse: web::Query<ParseQuery>,
+    format: FormatRequest,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[ResponseFormat::Json])?;
+    let forecast = get_forecast(&station, parse.options()).await?;
+    let formatter = time.formatter_or(DEFAULT_TIMEZONE);
+    let mut daily = aggregate_daily(&forecast, formatter.tz);
+    if weather_code.decode_weather {
+        decode_daily_weather(&mut daily);
+    }
+    daily.format_times(formatter);
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=1000"));
+    Ok(format.respond(response, &daily))
+}
+
+#[utoipa::path(
+    tag = "forecast",
+    params(("station" = String, Path, description = "The station's ID (e.g. `10865`)"), TimeQuery, UnitsQuery, ParseQuery),
+    responses(
+        (status = 200, description = "One all-day event per day", body = String, content_type = "text/calendar"),
+        (status = 404, description = "No forecast was found", body = DwdError),
+        (status = 502, description = "The upstream data didn't pass strict parsing", body = DwdError),
+    )
+)]
+#[get("/forecast/{station}/calendar.ics")]
+async fn handle_calendar(
+    station: web::Path<String>,
+    time: web::Query<TimeQuery>,
+    units: web::Query<UnitsQuery>,
+    parse: web::Query<ParseQuery>,
+) -> Result<HttpResponse, DwdError> {
+    let forecast = get_forecast(&station, parse.options()).await?;
+    let mut daily = aggregate_daily(&forecast, time.formatter_or(DEFAULT_TIMEZONE).tz);
+    decode_daily_weather(&mut daily);
     Ok(HttpResponse::Ok()
+        .content_type(ICALENDAR)
         .insert_header((header::CACHE_CONTROL, "max-age=1000"))
-        .json(forecast))
+        .body(daily_to_ics(&daily, &forecast.description, units.units)))
+}
+
+#[utoipa::path(
+    tag = "forecast",
+    params(
+        ("station" = String, Path, description = "The station's ID (e.g. `10865`)"),
+        ("extension" = String, Path, description = "`svg` or `png`"),
+        MeteogramQuery, TimeQuery, UnitsQuery, ParseQuery,
+    ),
+    responses(
+        (status = 200, description = "The meteogram", content(("image/svg+xml"), ("image/png"))),
+        (status = 400, description = "Invalid size or hours", body = DwdError),
+        (status = 404, description = "No forecast was found", body = DwdError),
+        (status = 502, description = "The upstream data didn't pass strict parsing", body = DwdError),
+    )
+)]
+#[get("/forecast/{station}/meteogram.{extension}")]
+async fn handle_meteogram(
+    path: web::Path<(String, String)>,
+    meteogram: web::Query<MeteogramQuery>,
+    time: web::Query<TimeQuery>,
+    units: web::Query<UnitsQuery>,
+    parse: web::Query<ParseQuery>,
+) -> Result<HttpResponse, DwdError> {
+    let (station, extension) = path.into_inner();
+    let format = ImageFormat::from_extension(&extension).ok_or(DwdError::NoRoute)?;
+    let forecast = get_forecast(&station, parse.options()).await?;
+    let tz = time.formatter_or(DEFAULT_TIMEZONE).tz;
+    let svg = render_meteogram(&forecast, &meteogram, units.units, tz)?;
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=1000"));
+    image_response(response, format, svg).await
 }
 
+#[utoipa::path(
+    tag = "stations",
+    params(FormatQuery),
+    responses(
+        (status = 200, description = "All MOSMIX stations", body = [MosmixStation], content(
+            ([MosmixStation] = "application/json"),
+            (FeatureCollection<MosmixStation> = "application/geo+json"),
+        )),
+        (status = 406, description = "The requested format isn't supported", body = DwdError),
+    )
+)]
 #[get("/stations")]
-async fn handle_get_stations() -> Result<HttpResponse, DwdError> {
+async fn handle_get_stations(format: FormatRequest) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[
+        ResponseFormat::Json,
+        ResponseFormat::NdJson,
+        ResponseFormat::GeoJson,
+    ])?;
     let stations = get_mosmix_stations().await?;
-    Ok(HttpResponse::Ok()
-        .insert_header((header::CACHE_CONTROL, "max-age=604800"))
-        .json(stations))
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=604800"));
+    Ok(format.list(response, stations))
+}
+
+#[utoipa::path(
+    tag = "stations",
+    params(FormatQuery),
+    responses(
+        (status = 200, description = "All stations with reports", body = [ReportStation], content(
+            ([ReportStation] = "application/json"),
+            (FeatureCollection<ReportStation> = "application/geo+json"),
+        )),
+        (status = 406, description = "The requested format isn't supported", body = DwdError),
+    )
+)]
+#[get("/report/stations")]
+async fn handle_get_report_stations(format: FormatRequest) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[
+        ResponseFormat::Json,
+        ResponseFormat::NdJson,
+        ResponseFormat::GeoJson,
+    ])?;
+    let stations = get_report_stations().await?;
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=3600"));
+    Ok(format.list(response, stations))
 }
 
+#[utoipa::path(
+    tag = "report",
+    params(("station" = String, Path, description = "The station's ID (e.g. `10865`)"), FormatQuery, WeatherCodeQuery, TimeQuery, UnitsQuery, ParseQuery, CsvQuery),
+    responses(
+        (status = 200, description = "The observations of the last days", body = WeatherReport, content(
+            (WeatherReport = "application/json"),
+            (String = "text/csv"),
+        )),
+        (status = 404, description = "No report was found", body = DwdError),
+        (status = 502, description = "The upstream data didn't pass strict parsing", body = DwdError),
+        (status = 406, description = "The requested format isn't supported", body = DwdError),
+    )
+)]
 #[get("/report/{station}")]
-async fn handle_get_report(station: web::Path<String>) -> Result<HttpResponse, DwdError> {
-    let report = get_weather_report(station.into_inner()).await?;
-    Ok(HttpResponse::Ok().json(report))
+async fn handle_get_report(
+    station: web::Path<String>,
+    format: FormatRequest,
+    params: ReportParams,
+    req: HttpRequest,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[
+        ResponseFormat::Json,
+        ResponseFormat::NdJson,
+        ResponseFormat::Csv,
+        ResponseFormat::Arrow,
+        ResponseFormat::Parquet,
+    ])?;
+    let report = load_report(station.clone(), &params).await?;
+    Ok(match format {
+        // only the records, `units` and `warnings` are only available as JSON
+        ResponseFormat::NdJson => ndjson_response(HttpResponse::Ok(), stream::iter(report.data)),
+        ResponseFormat::Csv => file_response(
+            HttpResponse::Ok(),
+            format,
+            &station,
+            report_to_csv(&report, &CsvQuery::from_query(req.query_string())?)?,
+        ),
+        ResponseFormat::Arrow | ResponseFormat::Parquet => {
+            let batch = table_to_record_batch(Table::from_report(&report), params.time.tz)?;
+            let body = encode_record_batch(&batch, format)?;
+            file_response(HttpResponse::Ok(), format, &station, body)
+        }
+        _ => format.respond(HttpResponse::Ok(), &report),
+    })
+}
+
+#[utoipa::path(
+    tag = "report",
+    params(FormatQuery, WeatherCodeQuery, TimeQuery, UnitsQuery, ParseQuery),
+    responses(
+        (status = 200, description = "The report or error of each station", body = [BatchItem<WeatherReport>]),
+        (status = 400, description = "Too many stations", body = DwdError),
+        (status = 406, description = "The requested format isn't supported", body = DwdError),
+    )
+)]
+#[post("/report/batch")]
+async fn handle_report_batch(
+    body: web::Json<BatchRequest>,
+    format: FormatRequest,
+    params: ReportParams,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[
+        ResponseFormat::Json,
+        ResponseFormat::NdJson,
+        ResponseFormat::Arrow,
+        ResponseFormat::Parquet,
+    ])?;
+    let stations = body.into_inner().stations()?;
+    let tz = params.time.tz;
+    let params = Rc::new(params);
+    let load = move |station: String| {
+        let params = params.clone();
+        async move { load_report(station, &params).await }
+    };
+    Ok(match format {
+        ResponseFormat::NdJson => ndjson_response(HttpResponse::Ok(), stream_batch(stations, load)),
+        ResponseFormat::Arrow | ResponseFormat::Parquet => {
+            let items = run_batch(stations, load).await;
+            let batch = batch_to_record_batch(items, Table::from_report, tz)?;
+            file_response(
+                HttpResponse::Ok(),
+                format,
+                "reports",
+                encode_record_batch(&batch, format)?,
+            )
+        }
+        _ => format.respond(HttpResponse::Ok(), &run_batch(stations, load).await),
+    })
+}
+
+#[utoipa::path(
+    tag = "report",
+    params(("station" = String, Path, description = "The station's ID (e.g. `10865`)"), FormatQuery, TimeQuery, UnitsQuery, ParseQuery),
+    responses(
+        (status = 200, description = "Observations followed by the forecast", body = Timeline),
+        (status = 404, description = "No forecast was found", body = DwdError),
+        (status = 502, description = "The upstream data didn't pass strict parsing", body = DwdError),
+    )
+)]
+#[get("/station/{station}/timeline")]
+async fn handle_timeline(
+    station: web::Path<String>,
+    time: web::Query<TimeQuery>,
+    units: web::Query<UnitsQuery>,
+    parse: web::Query<ParseQuery>,
+    format: FormatRequest,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[ResponseFormat::Json])?;
+    let mut timeline = get_timeline(&station, parse.options(), units.units).await?;
+    timeline.format_times(time.formatter());
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=1000"));
+    Ok(format.respond(response, &timeline))
+}
+
+#[utoipa::path(
+    tag = "report",
+    params(("station" = String, Path, description = "The station's ID (e.g. `10865`)"), FormatQuery, TimeQuery, UnitsQuery, ParseQuery),
+    responses(
+        (status = 200, description = "The latest observation, completed by the forecast", body = CurrentConditions),
+        (status = 404, description = "No forecast was found", body = DwdError),
+        (status = 502, description = "The upstream data didn't pass strict parsing", body = DwdError),
+    )
+)]
+#[get("/current/{station}")]
+async fn handle_current(
+    station: web::Path<String>,
+    time: web::Query<TimeQuery>,
+    units: web::Query<UnitsQuery>,
+    parse: web::Query<ParseQuery>,
+    format: FormatRequest,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[ResponseFormat::Json])?;
+    let mut current = get_current(&station, parse.options(), units.units).await?;
+    current.format_times(time.formatter());
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=600"));
+    Ok(format.respond(response, &current))
+}
+
+#[utoipa::path(
+    tag = "map",
+    params(MapQuery, FormatQuery, TimeQuery, UnitsQuery),
+    responses(
+        (status = 200, description = "The value of every station", body = MapSnapshot, content_type = "application/geo+json"),
+        (status = 400, description = "Unknown element, element not in MOSMIX_S or invalid time", body = DwdError),
+        (status = 404, description = "The time isn't covered by the latest run", body = DwdError),
+    )
+)]
+#[get("/map")]
+async fn handle_map(
+    query: web::Query<MapQuery>,
+    time: web::Query<TimeQuery>,
+    units: web::Query<UnitsQuery>,
+    format: FormatRequest,
+) -> Result<HttpResponse, DwdError> {
+    let format = format.select(&[ResponseFormat::GeoJson])?;
+    let mut snapshot = get_map(&query, units.units).await?;
+    snapshot.format_times(time.formatter());
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=600"));
+    Ok(match format {
+        ResponseFormat::MsgPack | ResponseFormat::Cbor => format.respond(response, &snapshot),
+        // the snapshot is always GeoJSON
+        _ => geojson_response(response, &snapshot),
+    })
+}
+
+#[utoipa::path(
+    tag = "map",
+    params(
+        ("extension" = String, Path, description = "`svg` or `png`"),
+        MapQuery, MapImageQuery, TimeQuery, UnitsQuery,
+    ),
+    responses(
+        (status = 200, description = "The stations within Germany", content(("image/svg+xml"), ("image/png"))),
+        (status = 400, description = "Unknown element, element not in MOSMIX_S, invalid time or width", body = DwdError),
+        (status = 404, description = "The time isn't covered by the latest run", body = DwdError),
+    )
+)]
+#[get("/map.{extension}")]
+async fn handle_map_image(
+    extension: web::Path<String>,
+    query: web::Query<MapQuery>,
+    image: web::Query<MapImageQuery>,
+    time: web::Query<TimeQuery>,
+    units: web::Query<UnitsQuery>,
+) -> Result<HttpResponse, DwdError> {
+    let format = ImageFormat::from_extension(&extension).ok_or(DwdError::NoRoute)?;
+    let snapshot = get_map(&query, units.units).await?;
+    let tz = time.formatter_or(DEFAULT_TIMEZONE).tz;
+    let svg = render_map(&snapshot, &image, tz)?;
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::CACHE_CONTROL, "max-age=600"));
+    image_response(response, format, svg).await
+}
+
+#[get("/openapi.json")]
+async fn handle_openapi() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(OPENAPI_JSON.as_str())
+}
+
+#[get("/docs")]
+async fn handle_docs() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(REDOC_HTML)
+}
+
+#[get("/schema")]
+async fn handle_schema_index() -> HttpResponse {
+    HttpResponse::Ok().json(schema_index())
+}
+
+#[get("/schema/{name}.json")]
+async fn handle_schema(name: web::Path<String>) -> Result<HttpResponse, DwdError> {
+    let schema = SCHEMAS
+        .get(name.as_str())
+        .ok_or_else(|| DwdError::UnknownSchema(name.into_inner()))?;
+    Ok(HttpResponse::Ok()
+        .content_type(SCHEMA_JSON)
+        .body(schema.as_str()))
 }
 
 #[actix_web::main]
@@ -45,9 +541,24 @@ async fn main() -> std::io::Result<()> {
                     // allow everyone to use this API
                     .add(("Access-Control-Allow-Origin", "*")),
             )
+            .service(handle_forecast_batch)
             .service(handle_station)
+            .service(handle_daily)
+            .service(handle_calendar)
+            .service(handle_meteogram)
             .service(handle_get_stations)
+            // has to be registered before `/report/{station}`
+            .service(handle_get_report_stations)
+            .service(handle_report_batch)
             .service(handle_get_report)
+            .service(handle_timeline)
+            .service(handle_current)
+            .service(handle_map)
+            .service(handle_map_image)
+            .service(handle_openapi)
+            .service(handle_docs)
+            .service(handle_schema_index)
+            .service(handle_schema)
             .default_service(web::to(|| async {
                 Err::<&'static str, _>(DwdError::NoRoute)
             }))
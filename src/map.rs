@@ -0,0 +1,424 @@
+use crate::{
+    elements::{element_by_key, resolve_key, DERIVED_ELEMENTS},
+    geojson::{Feature, Point},
+    kml::{deserialize_all_stations, AllStations, RunStation},
+    time_format::{TimeFormatter, Timestamp},
+    units::{Unit, UnitSystem},
+    DwdError,
+};
+use actix_web::web::Bytes;
+use chrono::DateTime;
+use lazy_static::lazy_static;
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Cursor,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+use utoipa::{IntoParams, ToSchema};
+use zip::ZipArchive;
+
+/// MOSMIX_S is issued every hour and has hourly time steps for ~5400 stations.
+/// (The all-stations MOSMIX_L file has many more elements, but is only issued every 6 hours and is much larger.)
+const ALL_STATIONS_URL: &str = "https://opendata.dwd.de/weather/local_forecasts/mos/MOSMIX_S/all_stations/kml/MOSMIX_S_LATEST_240.kmz";
+/// How long a run is used without asking the DWD for a newer one.
+const RUN_TTL: Duration = Duration::from_secs(5 * 60);
+
+lazy_static! {
+    /// The latest run. Requests only clone it out of the lock.
+    static ref LATEST_RUN: Mutex<Option<CachedRun>> = Mutex::new(None);
+    /// Held while the latest run is refreshed, so it's only downloaded once at a time.
+    static ref REFRESH: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+#[derive(Clone)]
+struct CachedRun {
+    run: Arc<AllStationsRun>,
+    /// The `Last-Modified` header of the file, if the DWD sent one
+    last_modified: Option<String>,
+    checked_at: Instant,
+}
+
+impl CachedRun {
+    fn is_fresh(&self) -> bool {
+        self.is_fresh_at(Instant::now())
+    }
+
+    fn is_fresh_at(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.checked_at) < RUN_TTL
+    }
+
+    /// The same run checked at `now`, if the DWD answered the `If-Modified-Since` request with `status`
+    /// that it hasn't changed.
+    fn revalidate(&self, status: StatusCode, now: Instant) -> Option<CachedRun> {
+        (status == StatusCode::NOT_MODIFIED && self.last_modified.is_some()).then(|| CachedRun {
+            checked_at: now,
+            ..self.clone()
+        })
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MapQuery {
+    /// A key or DWD name
+    pub element: String,
+    /// RFC 3339 or epoch milliseconds, the time step closest to now if missing
+    pub time: Option<String>,
+}
+
+/// The values of one element for each station of a run (`None` if a station doesn't have it).
+/// Values are in the DWD's units (`NaN` if missing) and `f32` to keep the memory footprint low.
+type StationValues = Vec<Option<Vec<f32>>>;
+
+/// One run of the all-stations product.
+pub struct AllStationsRun {
+    /// The compressed file, the values of an element are parsed from it when they're first requested
+    kmz: Bytes,
+    pub issue_time: i64,
+    pub time_steps: Vec<i64>,
+    pub stations: Vec<RunStation>,
+    /// The keys of the elements in the product
+    pub elements: BTreeSet<&'static str>,
+    values: Mutex<HashMap<&'static str, Arc<OnceCell<Arc<StationValues>>>>>,
+}
+
+/// A GeoJSON FeatureCollection with the element, unit and time step as foreign members.
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "type", rename = "FeatureCollection")]
+pub struct MapSnapshot {
+    pub element: &'static str,
+    pub unit: Unit,
+    pub time: Timestamp,
+    pub issue_time: Timestamp,
+    pub features: Vec<Feature<MapValue>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MapValue {
+    pub id: String,
+    pub name: String,
+    pub value: Option<f64>,
+}
+
+impl AllStationsRun {
+    /// A run parsed with the values of `element`.
+    fn new(kmz: Bytes, parsed: AllStations, element: &'static str) -> Self {
+        let mut values = HashMap::new();
+        if parsed.elements.contains(element) {
+            let parsed_values = Arc::new(parsed.values);
+            values.insert(element, Arc::new(OnceCell::new_with(Some(parsed_values))));
+        }
+        Self {
+            kmz,
+            issue_time: parsed.issue_time,
+            time_steps: parsed.time_steps,
+            stations: parsed.stations,
+            elements: parsed.elements,
+            values: Mutex::new(values),
+        }
+    }
+
+    /// The values of an element, which are parsed once when they're first requested.
+    async fn values(&self, key: &'static str) -> Result<Arc<StationValues>, DwdError> {
+        if !self.elements.contains(key) {
+            return Err(DwdError::ElementNotInProduct(key.to_owned()));
+        }
+        let cell = self
+            .values
+            .lock()
+            .map_err(|_| DwdError::InternalError)?
+            .entry(key)
+            .or_default()
+            .clone();
+        cell.get_or_try_init(|| async {
+            let parsed = parse_run(self.kmz.clone(), Some(key)).await?;
+            // the stations are the same in every pass, so the values line up with them
+            if parsed.stations.len() != self.stations.len() {
+                return Err(DwdError::InternalError);
+            }
+            Ok(Arc::new(parsed.values))
+        })
+        .await
+        .cloned()
+    }
+}
+
+async fn parse_run(kmz: Bytes, element: Option<&'static str>) -> Result<AllStations, DwdError> {
+    actix_web::rt::task::spawn_blocking(move || {
+        let mut zip = ZipArchive::new(Cursor::new(kmz)).map_err(|_| DwdError::BadZipFile)?;
+        let file = zip.by_index(0).map_err(|_| DwdError::NoZipEntry)?;
+        deserialize_all_stations(file, element)
+    })
+    .await
+    .map_err(|_| DwdError::InternalError)?
+}
+
+fn cached_run() -> Option<CachedRun> {
+    LATEST_RUN.lock().ok()?.clone()
+}
+
+fn cache_run(cached: CachedRun) {
+    if let Ok(mut latest) = LATEST_RUN.lock() {
+        *latest = Some(cached);
+    }
+}
+
+/// Returns the latest run. The DWD is asked for a newer one at most every [`RUN_TTL`] and only by one request
+/// at a time. A new run is parsed with the values of `element`, as those are needed right away.
+async fn get_latest_run(element: &'static str) -> Result<Arc<AllStationsRun>, DwdError> {
+    if let Some(cached) = cached_run().filter(CachedRun::is_fresh) {
+        return Ok(cached.run);
+    }
+    let _refresh = REFRESH.lock().await;
+    // another request might have refreshed the run while this one was waiting
+    let cached = cached_run();
+    if let Some(cached) = cached.as_ref().filter(|cached| cached.is_fresh()) {
+        return Ok(cached.run.clone());
+    }
+
+    match fetch_run(cached.as_ref(), element).await {
+        Ok(fetched) => {
+            let run = fetched.run.clone();
+            cache_run(fetched);
+            Ok(run)
+        }
+        // keep the previous run if the DWD can't be reached, but don't ask again for every request
+        Err(e) => {
+            let cached = cached.ok_or(e)?;
+            let run = cached.run.clone();
+            cache_run(CachedRun {
+                checked_at: Instant::now(),
+                ..cached
+            });
+            Ok(run)
+        }
+    }
+}
+
+/// Downloads the latest run, unless it's the same as `previous`.
+async fn fetch_run(
+    previous: Option<&CachedRun>,
+    element: &'static str,
+) -> Result<CachedRun, DwdError> {
+    let mut request = reqwest::Client::new().get(ALL_STATIONS_URL);
+    if let Some(last_modified) = previous.and_then(|cached| cached.last_modified.as_ref()) {
+        request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+    }
+    let res = request.send().await.map_err(|_| DwdError::NoForecast)?;
+    if let Some(revalidated) =
+        previous.and_then(|previous| previous.revalidate(res.status(), Instant::now()))
+    {
+        return Ok(revalidated);
+    }
+    let res = res.error_for_status().map_err(|_| DwdError::NoForecast)?;
+    let last_modified = res
+        .headers()
+        .get(header::LAST_MODIFIED)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let kmz = res.bytes().await.map_err(|_| DwdError::NoForecast)?;
+
+    let parsed = parse_run(kmz.clone(), Some(element)).await?;
+    Ok(CachedRun {
+        run: Arc::new(AllStationsRun::new(kmz, parsed, element)),
+        last_modified,
+        checked_at: Instant::now(),
+    })
+}
+
+pub async fn get_map(query: &MapQuery, system: UnitSystem) -> Result<MapSnapshot, DwdError> {
+    let key = resolve_key(&query.element)
+        .filter(|key| !DERIVED_ELEMENTS.contains_key(key))
+        .ok_or_else(|| DwdError::UnknownElement(query.element.clone()))?;
+    let time = query.time.as_deref().map(parse_time).transpose()?;
+    let run = get_latest_run(key).await?;
+    let values = run.values(key).await?;
+    let step = time_step(&run.time_steps, time, chrono::Utc::now().timestamp_millis())?;
+    map_snapshot(&run, key, &values, step, system)
+}
+
+/// Epoch milliseconds or RFC 3339 (e.g. `2024-06-01T15:00:00+02:00`).
+fn parse_time(time: &str) -> Result<i64, DwdError> {
+    time.parse::<i64>()
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(time)
+                .ok()
+                .map(|t| t.timestamp_millis())
+        })
+        .ok_or_else(|| DwdError::InvalidTime(time.to_owned()))
+}
+
+/// The index of the time step closest to `time`, which has to be within the run.
+/// Without a time, the step closest to `now` is used (e.g. the first one if the run starts in the future).
+fn time_step(time_steps: &[i64], time: Option<i64>, now: i64) -> Result<usize, DwdError> {
+    if let Some(time) = time {
+        let in_run = time_steps
+            .first()
+            .zip(time_steps.last())
+            .is_some_and(|(&first, &last)| (first..=last).contains(&time));
+        if !in_run {
+            return Err(DwdError::TimeNotInRun);
+        }
+    }
+    let time = time.unwrap_or(now);
+    time_steps
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, &t)| (t - time).abs())
+        .map(|(step, _)| step)
+        .ok_or(DwdError::TimeNotInRun)
+}
+
+/// The values of the time step `step` for every station.
+fn map_snapshot(
+    run: &AllStationsRun,
+    key: &'static str,
+    values: &StationValues,
+    step: usize,
+    system: UnitSystem,
+) -> Result<MapSnapshot, DwdError> {
+    let element = element_by_key(key).ok_or_else(|| DwdError::UnknownElement(key.to_owned()))?;
+    let unit = system
+        .target(element.quantity)
+        .filter(|&target| {
+            element
+                .unit
+                .convert_quantity(0.0, target, element.quantity)
+                .is_some()
+        })
+        .unwrap_or(element.unit);
+    let features: Vec<_> = run
+        .stations
+        .iter()
+        .zip(values)
+        .filter_map(|(station, values)| {
+            let value = *values.as_ref()?.get(step)?;
+            Some(Feature {
+                geometry: Some(Point::new(
+                    station.location.longitude,
+                    station.location.latitude,
+                    station.location.elevation,
+                )),
+                properties: MapValue {
+                    id: station.id.clone(),
+                    name: station.name.clone(),
+                    // the DWD uses two decimals, f32 -> f64 adds noise (e.g. 289.149994)
+                    value: (!value.is_nan())
+                        .then(|| (value as f64 * 100.0).round() / 100.0)
+                        .and_then(|value| {
+                            element.unit.convert_quantity(value, unit, element.quantity)
+                        }),
+                },
+            })
+        })
+        .collect();
+    if features.is_empty() {
+        return Err(DwdError::NoMapData);
+    }
+
+    Ok(MapSnapshot {
+        element: key,
+        unit,
+        time: Timestamp::from_millis(run.time_steps[step]),
+        issue_time: Timestamp::from_millis(run.issue_time),
+        features,
+    })
+}
+
+impl MapSnapshot {
+    pub fn format_times(&mut self, formatter: TimeFormatter) {
+        self.time.format_with(formatter);
+        self.issue_time.format_with(formatter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3_600_000;
+    const TIME_STEPS: &[i64] = &[10 * HOUR, 11 * HOUR, 12 * HOUR];
+
+    fn cached_run(last_modified: Option<&str>, checked_at: Instant) -> CachedRun {
+        let parsed = AllStations {
+            issue_time: 9 * HOUR,
+            time_steps: TIME_STEPS.to_vec(),
+            stations: Vec::new(),
+            elements: BTreeSet::from(["temp"]),
+            values: Vec::new(),
+        };
+        CachedRun {
+            run: Arc::new(AllStationsRun::new(Bytes::new(), parsed, "temp")),
+            last_modified: last_modified.map(str::to_owned),
+            checked_at,
+        }
+    }
+
+    #[test]
+    fn run_is_fresh_until_the_ttl() {
+        let checked_at = Instant::now();
+        let cached = cached_run(None, checked_at);
+        assert!(cached.is_fresh_at(checked_at));
+        assert!(cached.is_fresh_at(checked_at + RUN_TTL - Duration::from_secs(1)));
+        assert!(!cached.is_fresh_at(checked_at + RUN_TTL));
+    }
+
+    #[test]
+    fn unmodified_run_is_revalidated() {
+        let checked_at = Instant::now();
+        let later = checked_at + RUN_TTL * 2;
+        let cached = cached_run(Some("Mon, 10 Jun 2024 03:30:00 GMT"), checked_at);
+
+        let revalidated = cached.revalidate(StatusCode::NOT_MODIFIED, later).unwrap();
+        assert!(Arc::ptr_eq(&revalidated.run, &cached.run));
+        assert_eq!(revalidated.last_modified, cached.last_modified);
+        assert!(revalidated.is_fresh_at(later));
+        // a new run has to be downloaded
+        assert!(cached.revalidate(StatusCode::OK, later).is_none());
+    }
+
+    #[test]
+    fn run_without_last_modified_is_downloaded_again() {
+        let cached = cached_run(None, Instant::now());
+        assert!(cached
+            .revalidate(StatusCode::NOT_MODIFIED, Instant::now())
+            .is_none());
+    }
+
+    #[test]
+    fn requested_time_uses_the_closest_step() {
+        assert_eq!(time_step(TIME_STEPS, Some(10 * HOUR), 0).unwrap(), 0);
+        assert_eq!(
+            time_step(TIME_STEPS, Some(11 * HOUR - 60_000), 0).unwrap(),
+            1
+        );
+        assert_eq!(time_step(TIME_STEPS, Some(12 * HOUR), 0).unwrap(), 2);
+    }
+
+    #[test]
+    fn requested_time_has_to_be_in_the_run() {
+        for time in [10 * HOUR - 1, 12 * HOUR + 1] {
+            assert!(matches!(
+                time_step(TIME_STEPS, Some(time), 11 * HOUR),
+                Err(DwdError::TimeNotInRun)
+            ));
+        }
+    }
+
+    #[test]
+    fn default_time_is_clamped_to_the_run() {
+        // just after a run was issued, its first time step is in the future
+        assert_eq!(time_step(TIME_STEPS, None, 9 * HOUR + 1).unwrap(), 0);
+        assert_eq!(time_step(TIME_STEPS, None, 11 * HOUR + 1).unwrap(), 1);
+        assert_eq!(time_step(TIME_STEPS, None, 20 * HOUR).unwrap(), 2);
+        assert!(matches!(
+            time_step(&[], None, 0),
+            Err(DwdError::TimeNotInRun)
+        ));
+    }
+}
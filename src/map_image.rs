@@ -0,0 +1,392 @@
+use crate::{map::MapSnapshot, DwdError};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::fmt::Write;
+use utoipa::IntoParams;
+
+/// The area that's drawn (longitude and latitude).
+const WEST: f64 = 5.5;
+const EAST: f64 = 15.5;
+const SOUTH: f64 = 47.0;
+const NORTH: f64 = 55.3;
+/// Longitudes are scaled by the cosine of the central latitude, so Germany isn't stretched.
+const CENTRAL_LATITUDE: f64 = 51.2;
+
+const TITLE_HEIGHT: f64 = 28.0;
+const LEGEND_HEIGHT: f64 = 44.0;
+
+/// A simplified outline of Germany (longitude, latitude), clockwise from the Danish border.
+const GERMANY_OUTLINE: &[(f64, f64)] = &[
+    (8.66, 54.91),
+    (9.42, 54.83),
+    (9.87, 54.76),
+    (10.03, 54.55),
+    (10.82, 54.32),
+    (11.1, 54.42),
+    (11.09, 54.01),
+    (10.87, 53.96),
+    (11.47, 54.0),
+    (12.1, 54.18),
+    (12.5, 54.47),
+    (13.08, 54.43),
+    (13.4, 54.68),
+    (13.69, 54.44),
+    (13.8, 54.1),
+    (14.22, 53.93),
+    (14.41, 53.33),
+    (14.13, 52.83),
+    (14.64, 52.57),
+    (14.55, 52.35),
+    (14.72, 51.95),
+    (14.95, 51.45),
+    (15.04, 51.11),
+    (14.82, 50.87),
+    (14.56, 50.92),
+    (14.25, 50.87),
+    (13.9, 50.73),
+    (13.45, 50.6),
+    (12.95, 50.41),
+    (12.5, 50.35),
+    (12.1, 50.32),
+    (12.2, 50.1),
+    (12.45, 49.75),
+    (12.55, 49.6),
+    (12.95, 49.34),
+    (13.4, 49.05),
+    (13.84, 48.77),
+    (13.73, 48.51),
+    (13.46, 48.57),
+    (13.03, 48.27),
+    (12.75, 48.12),
+    (12.95, 47.95),
+    (13.0, 47.8),
+    (13.05, 47.5),
+    (12.8, 47.55),
+    (12.5, 47.68),
+    (12.2, 47.6),
+    (11.6, 47.58),
+    (10.98, 47.42),
+    (10.45, 47.55),
+    (10.2, 47.27),
+    (9.95, 47.54),
+    (9.7, 47.55),
+    (9.17, 47.66),
+    (8.9, 47.65),
+    (8.6, 47.8),
+    (8.4, 47.6),
+    (7.95, 47.55),
+    (7.59, 47.59),
+    (7.52, 47.9),
+    (7.57, 48.3),
+    (7.8, 48.58),
+    (8.2, 48.97),
+    (7.6, 49.05),
+    (7.0, 49.15),
+    (6.7, 49.2),
+    (6.36, 49.47),
+    (6.5, 49.8),
+    (6.13, 50.13),
+    (6.4, 50.33),
+    (6.03, 50.73),
+    (5.87, 51.05),
+    (6.17, 51.2),
+    (6.07, 51.85),
+    (6.72, 51.9),
+    (7.05, 52.23),
+    (6.7, 52.5),
+    (7.07, 52.65),
+    (7.2, 53.25),
+    (7.0, 53.6),
+    (8.0, 53.7),
+    (8.1, 53.55),
+    (8.5, 53.55),
+    (8.7, 53.87),
+    (9.0, 53.9),
+    (8.85, 54.13),
+    (8.6, 54.35),
+    (9.05, 54.48),
+    (8.6, 54.5),
+];
+
+/// From cold to warm, values are spread evenly between the lowest and highest value.
+const PALETTE: &[(u8, u8, u8)] = &[
+    (44, 123, 182),
+    (171, 217, 233),
+    (255, 255, 191),
+    (253, 174, 97),
+    (215, 25, 28),
+];
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MapImageQuery {
+    #[serde(default = "default_width")]
+    pub width: u32,
+}
+
+fn default_width() -> u32 {
+    600
+}
+
+/// Longitude and latitude to pixels, below the title.
+struct Projection {
+    /// Pixels per degree of latitude
+    scale: f64,
+}
+
+impl Projection {
+    fn new(width: f64) -> Self {
+        Projection {
+            scale: width / ((EAST - WEST) * CENTRAL_LATITUDE.to_radians().cos()),
+        }
+    }
+
+    fn map_height(&self) -> f64 {
+        (NORTH - SOUTH) * self.scale
+    }
+
+    fn point(&self, lon: f64, lat: f64) -> (f64, f64) {
+        (
+            (lon - WEST) * CENTRAL_LATITUDE.to_radians().cos() * self.scale,
+            TITLE_HEIGHT + (NORTH - lat) * self.scale,
+        )
+    }
+}
+
+/// The stations within the bounding box that have a value, as longitude, latitude and value.
+fn stations_in_bounds(snapshot: &MapSnapshot) -> Vec<(f64, f64, f64)> {
+    snapshot
+        .features
+        .iter()
+        .filter_map(|feature| {
+            let coordinates = &feature.geometry.as_ref()?.coordinates;
+            let (lon, lat) = (*coordinates.first()?, *coordinates.get(1)?);
+            let in_bounds = (WEST..=EAST).contains(&lon) && (SOUTH..=NORTH).contains(&lat);
+            in_bounds.then_some((lon, lat, feature.properties.value?))
+        })
+        .collect()
+}
+
+fn color(t: f64) -> String {
+    let t = t.clamp(0.0, 1.0) * (PALETTE.len() - 1) as f64;
+    let i = (t.floor() as usize).min(PALETTE.len() - 2);
+    let f = t - i as f64;
+    let (a, b) = (PALETTE[i], PALETTE[i + 1]);
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        mix(a.0, b.0),
+        mix(a.1, b.1),
+        mix(a.2, b.2)
+    )
+}
+
+/// Draws the stations of the snapshot within Germany's bounding box as dots over the outline,
+/// colored by their value. The time is shown in `tz`.
+pub fn render_map(
+    snapshot: &MapSnapshot,
+    query: &MapImageQuery,
+    tz: Tz,
+) -> Result<String, DwdError> {
+    if !(200..=3000).contains(&query.width) {
+        return Err(DwdError::InvalidImageOptions(
+            "width has to be between 200 and 3000",
+        ));
+    }
+    let width = query.width as f64;
+    let projection = Projection::new(width);
+    let map_height = projection.map_height();
+    let height = (TITLE_HEIGHT + map_height + LEGEND_HEIGHT).round();
+
+    let stations = stations_in_bounds(snapshot);
+    let (min, max) = stations
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), &(_, _, v)| {
+            (lo.min(v), hi.max(v))
+        });
+    let normalize = |v: f64| {
+        if max > min {
+            (v - min) / (max - min)
+        } else {
+            0.5
+        }
+    };
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="12">"#
+    );
+    svg.push_str(r##"<rect width="100%" height="100%" fill="#f4f7fa"/>"##);
+
+    let time = DateTime::from_timestamp_millis(snapshot.time.millis())
+        .map(|t| {
+            t.with_timezone(&tz)
+                .format("%a %d.%m.%Y %H:%M %Z")
+                .to_string()
+        })
+        .unwrap_or_default();
+    let _ = write!(
+        svg,
+        r#"<text x="8" y="19" font-size="14" font-weight="bold">{} ({})</text><text x="{:.0}" y="19" text-anchor="end">{time}</text>"#,
+        snapshot.element,
+        snapshot.unit.symbol(),
+        width - 8.0
+    );
+
+    let outline: Vec<String> = GERMANY_OUTLINE
+        .iter()
+        .map(|&(lon, lat)| {
+            let (x, y) = projection.point(lon, lat);
+            format!("{x:.1},{y:.1}")
+        })
+        .collect();
+    let _ = write!(
+        svg,
+        r##"<polygon points="{}" fill="#fff" stroke="#555" stroke-width="1.2" stroke-linejoin="round"/>"##,
+        outline.join(" ")
+    );
+
+    let radius = (width / 150.0).max(2.5);
+    for &(lon, lat, value) in &stations {
+        let (x, y) = projection.point(lon, lat);
+        let _ = write!(
+            svg,
+            r##"<circle cx="{x:.1}" cy="{y:.1}" r="{radius:.1}" fill="{}" stroke="#333" stroke-width="0.5"/>"##,
+            color(normalize(value))
+        );
+    }
+
+    // legend: a gradient from the lowest to the highest value
+    if !stations.is_empty() {
+        let legend_y = TITLE_HEIGHT + map_height + 8.0;
+        let (left, right) = (width * 0.2, width * 0.8);
+        svg.push_str(r#"<defs><linearGradient id="palette">"#);
+        for (i, _) in PALETTE.iter().enumerate() {
+            let t = i as f64 / (PALETTE.len() - 1) as f64;
+            let _ = write!(svg, r#"<stop offset="{t:.2}" stop-color="{}"/>"#, color(t));
+        }
+        svg.push_str("</linearGradient></defs>");
+        let _ = write!(
+            svg,
+            r##"<rect x="{left:.1}" y="{legend_y:.1}" width="{:.1}" height="12" fill="url(#palette)" stroke="#333" stroke-width="0.5"/>"##,
+            right - left
+        );
+        let label_y = legend_y + 28.0;
+        let _ = write!(
+            svg,
+            r#"<text x="{left:.1}" y="{label_y:.1}" text-anchor="middle">{min}</text><text x="{right:.1}" y="{label_y:.1}" text-anchor="middle">{max}</text>"#
+        );
+    }
+
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geojson::{Feature, Point},
+        map::MapValue,
+        time_format::Timestamp,
+        units::Unit,
+    };
+
+    fn station(lon: f64, lat: f64, value: Option<f64>) -> Feature<MapValue> {
+        Feature {
+            geometry: Some(Point::new(lon, lat, None)),
+            properties: MapValue {
+                id: String::new(),
+                name: String::new(),
+                value,
+            },
+        }
+    }
+
+    fn snapshot() -> MapSnapshot {
+        MapSnapshot {
+            element: "temp",
+            unit: Unit::Celsius,
+            time: Timestamp::from_millis(1_792_875_600_000),
+            issue_time: Timestamp::from_millis(1_792_864_800_000),
+            features: vec![
+                station(11.55, 48.13, Some(4.0)),
+                station(13.4, 52.52, Some(12.0)),
+                // Paris, Copenhagen and Vienna are outside the bounding box
+                station(2.35, 48.86, Some(20.0)),
+                station(12.57, 55.68, Some(20.0)),
+                station(16.37, 48.21, Some(20.0)),
+                station(8.68, 50.11, None),
+                Feature {
+                    geometry: None,
+                    properties: MapValue {
+                        id: String::new(),
+                        name: String::new(),
+                        value: Some(20.0),
+                    },
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn projection() {
+        let width = 600.0;
+        let projection = Projection::new(width);
+        let (west, north) = projection.point(WEST, NORTH);
+        let (east, south) = projection.point(EAST, SOUTH);
+        assert!(west.abs() < 1e-9 && (north - TITLE_HEIGHT).abs() < 1e-9);
+        assert!((east - width).abs() < 1e-9);
+        assert!((south - TITLE_HEIGHT - projection.map_height()).abs() < 1e-9);
+
+        for &(lon, lat) in GERMANY_OUTLINE {
+            let (x, y) = projection.point(lon, lat);
+            assert!((0.0..=width).contains(&x), "{lon} is outside");
+            assert!((TITLE_HEIGHT..=south).contains(&y), "{lat} is outside");
+        }
+    }
+
+    #[test]
+    fn stations_outside_are_dropped() {
+        assert_eq!(
+            stations_in_bounds(&snapshot()),
+            [(11.55, 48.13, 4.0), (13.4, 52.52, 12.0)]
+        );
+    }
+
+    #[test]
+    fn palette() {
+        assert_eq!(color(0.0), "#2c7bb6");
+        assert_eq!(color(0.5), "#ffffbf");
+        assert_eq!(color(1.0), "#d7191c");
+        // halfway between the first two colors
+        assert_eq!(color(0.125), "#6caad0");
+        assert_eq!(color(-1.0), color(0.0));
+        assert_eq!(color(2.0), color(1.0));
+    }
+
+    #[test]
+    fn width_limits() {
+        for width in [199, 3001] {
+            assert!(matches!(
+                render_map(&snapshot(), &MapImageQuery { width }, Tz::UTC),
+                Err(DwdError::InvalidImageOptions(_))
+            ));
+        }
+        for width in [200, 3000] {
+            assert!(render_map(&snapshot(), &MapImageQuery { width }, Tz::UTC).is_ok());
+        }
+    }
+
+    #[test]
+    fn render() {
+        let svg = render_map(&snapshot(), &MapImageQuery { width: 600 }, Tz::UTC).unwrap();
+        assert_eq!(svg.matches("<circle").count(), 2);
+        // the lowest and highest value are at the ends of the palette
+        assert!(svg.contains(r##"fill="#2c7bb6" stroke="#333""##));
+        assert!(svg.contains(r##"fill="#d7191c" stroke="#333""##));
+        assert!(svg.contains(">temp (°C)<"));
+    }
+}
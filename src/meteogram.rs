@@ -0,0 +1,584 @@
+use crate::{
+    units::{Quantity, Unit, UnitSystem},
+    weather_forecast::Forecast,
+    DwdError,
+};
+use chrono::{DateTime, Timelike};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::fmt::Write;
+use utoipa::IntoParams;
+
+const HOUR: i64 = 60 * 60 * 1000;
+
+const TITLE_HEIGHT: f64 = 24.0;
+const CLOUD_BAND_HEIGHT: f64 = 14.0;
+const GAP: f64 = 6.0;
+const WIND_ROW_HEIGHT: f64 = 40.0;
+/// The hour labels above and the date labels below the wind barbs
+const AXIS_HEIGHT: f64 = 30.0;
+const MARGIN_X: f64 = 44.0;
+
+const TEMP_COLOR: &str = "#d62728";
+const DEW_POINT_COLOR: &str = "#2ca02c";
+const PRECIPITATION_COLOR: &str = "#1f77b4";
+const GRID_COLOR: &str = "#ddd";
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MeteogramQuery {
+    /// How many hours after the first time step are shown
+    #[serde(default = "default_hours")]
+    pub hours: u32,
+    #[serde(default = "default_width")]
+    pub width: u32,
+    #[serde(default = "default_height")]
+    pub height: u32,
+}
+
+fn default_hours() -> u32 {
+    48
+}
+
+fn default_width() -> u32 {
+    800
+}
+
+fn default_height() -> u32 {
+    400
+}
+
+impl MeteogramQuery {
+    fn validate(&self) -> Result<(), DwdError> {
+        if !(1..=240).contains(&self.hours) {
+            return Err(DwdError::InvalidImageOptions(
+                "hours has to be between 1 and 240",
+            ));
+        }
+        if !(300..=4000).contains(&self.width) || !(200..=3000).contains(&self.height) {
+            return Err(DwdError::InvalidImageOptions(
+                "width has to be between 300 and 4000, height between 200 and 3000",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The columns of the meteogram, converted to their display units.
+struct Series {
+    times: Vec<i64>,
+    temp: Vec<Option<f64>>,
+    dew_point: Vec<Option<f64>>,
+    precipitation: Vec<Option<f64>>,
+    /// Always in knots, as that's what barbs show
+    wind_speed: Vec<Option<f64>>,
+    wind_direction: Vec<Option<f64>>,
+    cloud_cover: Vec<Option<f64>>,
+    temp_unit: Unit,
+    precipitation_unit: Unit,
+}
+
+impl Series {
+    fn new(forecast: &Forecast, hours: u32, system: UnitSystem) -> Self {
+        let system = match system {
+            UnitSystem::Raw => UnitSystem::Metric,
+            system => system,
+        };
+        let times: Vec<i64> = forecast
+            .column("time_steps")
+            .into_iter()
+            .map_while(|t| t.map(|t| t as i64))
+            .collect();
+        let start = times.first().copied().unwrap_or_default();
+        let n = times
+            .iter()
+            .take_while(|&&t| t <= start + hours as i64 * HOUR)
+            .count();
+        let column = |key: &str, to: Option<Unit>| -> (Vec<Option<f64>>, Option<Unit>) {
+            let from = forecast.units.get(key).copied();
+            let to = to.or(from);
+            let values = forecast.column(key)[..n]
+                .iter()
+                .map(|v| match (from, to) {
+                    (Some(from), Some(to)) => from.convert((*v)?, to),
+                    _ => *v,
+                })
+                .collect();
+            (values, to)
+        };
+
+        let (temp, temp_unit) = column("temp", system.target(Quantity::Temperature));
+        let (dew_point, _) = column("dew_point", system.target(Quantity::Temperature));
+        let (precipitation, precipitation_unit) = column(
+            "precipitation_1h_significant_weather",
+            system.target(Quantity::Precipitation),
+        );
+        Series {
+            times: times[..n].to_vec(),
+            temp,
+            dew_point,
+            precipitation,
+            wind_speed: column("wind_speed", Some(Unit::Knots)).0,
+            wind_direction: column("wind_direction", None).0,
+            cloud_cover: column("total_cloud_cover", None).0,
+            temp_unit: temp_unit.unwrap_or(Unit::Kelvin),
+            precipitation_unit: precipitation_unit.unwrap_or(Unit::KilogramsPerSquareMeter),
+        }
+    }
+}
+
+/// A step of 1, 2, 5, 10, ... so the range has at most `max_ticks` ticks.
+fn nice_step(range: f64, max_ticks: f64) -> f64 {
+    let raw = (range / max_ticks).max(f64::EPSILON);
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// `lo..=hi` in steps of `step`, rounded to avoid labels like `0.30000000000000004`.
+fn ticks(lo: f64, hi: f64, step: f64) -> impl Iterator<Item = f64> {
+    let n = ((hi - lo) / step).round() as usize;
+    (0..=n).map(move |i| ((lo + i as f64 * step) * 1000.0).round() / 1000.0)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A path through the values, interrupted where values are missing.
+fn line_path(points: impl Iterator<Item = Option<(f64, f64)>>) -> String {
+    let mut path = String::new();
+    let mut pen_down = false;
+    for point in points {
+        match point {
+            Some((x, y)) => {
+                let command = if pen_down { 'L' } else { 'M' };
+                let _ = write!(path, "{command}{x:.1},{y:.1}");
+                pen_down = true;
+            }
+            None => pen_down = false,
+        }
+    }
+    path
+}
+
+/// A wind barb pointing in the direction the wind is coming from (`direction` in degrees, `knots` rounded to 5).
+fn wind_barb(svg: &mut String, x: f64, y: f64, knots: f64, direction: f64) {
+    let mut knots = (knots / 5.0).round() as u32 * 5;
+    if knots == 0 {
+        let _ = write!(
+            svg,
+            r##"<circle cx="{x:.1}" cy="{y:.1}" r="3" fill="none" stroke="#333"/>"##
+        );
+        return;
+    }
+    const LENGTH: f64 = 16.0;
+    let _ = write!(
+        svg,
+        r##"<g transform="translate({x:.1},{y:.1}) rotate({direction:.0})" stroke="#333" fill="#333"><line x1="0" y1="0" x2="0" y2="-{LENGTH}"/>"##
+    );
+    // from the tip towards the station: pennants (50), full barbs (10), half barbs (5)
+    let mut offset = -LENGTH;
+    while knots >= 50 {
+        let _ = write!(
+            svg,
+            r#"<polygon points="0,{:.1} 7,{:.1} 0,{:.1}" stroke="none"/>"#,
+            offset,
+            offset + 2.0,
+            offset + 4.0
+        );
+        offset += 5.0;
+        knots -= 50;
+    }
+    while knots >= 10 {
+        let _ = write!(
+            svg,
+            r#"<line x1="0" y1="{offset:.1}" x2="7" y2="{:.1}"/>"#,
+            offset - 3.0
+        );
+        offset += 3.0;
+        knots -= 10;
+    }
+    if knots >= 5 {
+        // a lone half barb isn't drawn at the tip, so it's not mistaken for a full one
+        if offset == -LENGTH {
+            offset += 3.0;
+        }
+        let _ = write!(
+            svg,
+            r#"<line x1="0" y1="{offset:.1}" x2="3.5" y2="{:.1}"/>"#,
+            offset - 1.5
+        );
+    }
+    svg.push_str("</g>");
+}
+
+/// Renders temperature and dew point lines, precipitation bars, wind barbs and a cloud cover band.
+/// Times are labelled in `tz`.
+pub fn render_meteogram(
+    forecast: &Forecast,
+    query: &MeteogramQuery,
+    system: UnitSystem,
+    tz: Tz,
+) -> Result<String, DwdError> {
+    query.validate()?;
+    let series = Series::new(forecast, query.hours, system);
+    let (Some(&start), Some(&end)) = (series.times.first(), series.times.last()) else {
+        return Err(DwdError::NoForecast);
+    };
+    let end = end.max(start + HOUR);
+
+    let (width, height) = (query.width as f64, query.height as f64);
+    let plot_left = MARGIN_X;
+    let plot_right = width - MARGIN_X;
+    let cloud_top = TITLE_HEIGHT;
+    let plot_top = cloud_top + CLOUD_BAND_HEIGHT + GAP;
+    let plot_bottom = height - AXIS_HEIGHT - WIND_ROW_HEIGHT;
+    let x =
+        |t: i64| plot_left + (t - start) as f64 / (end - start) as f64 * (plot_right - plot_left);
+    let px_per_hour = x(start + HOUR) - x(start);
+
+    // scales
+    let temps = series
+        .temp
+        .iter()
+        .chain(&series.dew_point)
+        .flatten()
+        .copied();
+    let (t_min, t_max) = temps.fold((f64::MAX, f64::MIN), |(lo, hi), t| (lo.min(t), hi.max(t)));
+    let (t_min, t_max) = if t_min > t_max {
+        (0.0, 10.0)
+    } else {
+        (t_min, t_max)
+    };
+    let t_step = nice_step((t_max - t_min).max(1.0), 6.0);
+    let t_lo = (t_min / t_step).floor() * t_step;
+    let t_hi = ((t_max / t_step).ceil() * t_step).max(t_lo + t_step);
+    let y_temp = |t: f64| plot_bottom - (t - t_lo) / (t_hi - t_lo) * (plot_bottom - plot_top);
+
+    let p_max = series
+        .precipitation
+        .iter()
+        .flatten()
+        .copied()
+        .fold(0.0, f64::max);
+    // at least 2 mm, so drizzle doesn't look like a downpour
+    let p_floor = Unit::Millimeters
+        .convert(2.0, series.precipitation_unit)
+        .unwrap_or(2.0);
+    let p_step = nice_step(p_max.max(p_floor), 4.0);
+    let p_hi = (p_max.max(p_floor) / p_step).ceil() * p_step;
+    let y_precipitation = |p: f64| plot_bottom - p / p_hi * (plot_bottom - plot_top);
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="11">"#
+    );
+    svg.push_str(r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    // title and legend
+    let _ = write!(
+        svg,
+        r#"<text x="{plot_left}" y="16" font-size="13" font-weight="bold">{}</text>"#,
+        escape(&forecast.description)
+    );
+    let _ = write!(
+        svg,
+        r##"<text x="{plot_right}" y="16" text-anchor="end"><tspan fill="{TEMP_COLOR}">Temperature</tspan> <tspan fill="{DEW_POINT_COLOR}">Dew point</tspan> <tspan fill="{PRECIPITATION_COLOR}">Precipitation</tspan> <tspan fill="#888">Cloud cover</tspan></text>"##
+    );
+
+    // cloud cover band
+    for (i, &t) in series.times.iter().enumerate() {
+        let Some(cover) = series.cloud_cover[i] else {
+            continue;
+        };
+        let x0 = x(t) - px_per_hour / 2.0;
+        let _ = write!(
+            svg,
+            r##"<rect x="{:.1}" y="{cloud_top}" width="{:.1}" height="{CLOUD_BAND_HEIGHT}" fill="#666" fill-opacity="{:.2}"/>"##,
+            x0.max(plot_left),
+            (x0 + px_per_hour).min(plot_right) - x0.max(plot_left),
+            (cover / 100.0).clamp(0.0, 1.0)
+        );
+    }
+
+    // horizontal grid with the temperature (left) and precipitation (right) axes
+    for t in ticks(t_lo, t_hi, t_step) {
+        let y = y_temp(t);
+        let _ = write!(
+            svg,
+            r#"<line x1="{plot_left}" y1="{y:.1}" x2="{plot_right}" y2="{y:.1}" stroke="{GRID_COLOR}"/><text x="{:.1}" y="{:.1}" text-anchor="end" fill="{TEMP_COLOR}">{t}</text>"#,
+            plot_left - 4.0,
+            y + 4.0
+        );
+    }
+    let _ = write!(
+        svg,
+        r#"<text x="{:.1}" y="{:.1}" text-anchor="end" fill="{TEMP_COLOR}">{}</text>"#,
+        plot_left - 4.0,
+        plot_top - 8.0,
+        series.temp_unit.symbol()
+    );
+    for p in ticks(0.0, p_hi, p_step) {
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" fill="{PRECIPITATION_COLOR}">{p}</text>"#,
+            plot_right + 4.0,
+            y_precipitation(p) + 4.0
+        );
+    }
+    let _ = write!(
+        svg,
+        r#"<text x="{:.1}" y="{:.1}" fill="{PRECIPITATION_COLOR}">{}</text>"#,
+        plot_right + 4.0,
+        plot_top - 8.0,
+        series.precipitation_unit.symbol()
+    );
+
+    // time axis: a line at each local midnight, labels every few hours (depending on the width)
+    let label_hours = [3, 6, 12, 24]
+        .into_iter()
+        .find(|&h| h as f64 * px_per_hour >= 30.0)
+        .unwrap_or(24);
+    for &t in &series.times {
+        let Some(local) = DateTime::from_timestamp_millis(t).map(|d| d.with_timezone(&tz)) else {
+            continue;
+        };
+        let xt = x(t);
+        if local.hour() == 0 {
+            let _ = write!(
+                svg,
+                r##"<line x1="{xt:.1}" y1="{plot_top}" x2="{xt:.1}" y2="{plot_bottom}" stroke="#999"/><text x="{:.1}" y="{:.1}" font-weight="bold">{}</text>"##,
+                xt + 2.0,
+                height - 6.0,
+                local.format("%a %d.%m.")
+            );
+        }
+        if local.hour() % label_hours == 0 {
+            let _ = write!(
+                svg,
+                r##"<line x1="{xt:.1}" y1="{plot_bottom}" x2="{xt:.1}" y2="{:.1}" stroke="#333"/><text x="{xt:.1}" y="{:.1}" text-anchor="middle">{}</text>"##,
+                plot_bottom + 4.0,
+                plot_bottom + 14.0,
+                local.format("%H")
+            );
+        }
+    }
+
+    // precipitation of the preceding hour
+    for (i, &t) in series.times.iter().enumerate() {
+        let Some(p) = series.precipitation[i].filter(|&p| p > 0.0) else {
+            continue;
+        };
+        let x0 = x(t - HOUR).max(plot_left);
+        let y = y_precipitation(p.min(p_hi));
+        let _ = write!(
+            svg,
+            r#"<rect x="{:.1}" y="{y:.1}" width="{:.1}" height="{:.1}" fill="{PRECIPITATION_COLOR}" fill-opacity="0.6"/>"#,
+            x0 + 0.5,
+            (x(t) - x0 - 1.0).max(0.5),
+            plot_bottom - y
+        );
+    }
+
+    // temperature and dew point
+    let points = |values: &[Option<f64>]| {
+        line_path(
+            series
+                .times
+                .iter()
+                .zip(values)
+                .map(|(&t, v)| v.map(|v| (x(t), y_temp(v)))),
+        )
+    };
+    let _ = write!(
+        svg,
+        r#"<path d="{}" fill="none" stroke="{DEW_POINT_COLOR}" stroke-width="1.5" stroke-dasharray="4 3"/>"#,
+        points(&series.dew_point)
+    );
+    let _ = write!(
+        svg,
+        r#"<path d="{}" fill="none" stroke="{TEMP_COLOR}" stroke-width="2"/>"#,
+        points(&series.temp)
+    );
+    let _ = write!(
+        svg,
+        r##"<rect x="{plot_left}" y="{plot_top}" width="{:.1}" height="{:.1}" fill="none" stroke="#333"/>"##,
+        plot_right - plot_left,
+        plot_bottom - plot_top
+    );
+
+    // wind barbs, at least 32px apart
+    let stride = (32.0 / px_per_hour).ceil().max(1.0) as usize;
+    // between the hour labels and the date labels
+    let wind_y = plot_bottom + 16.0 + WIND_ROW_HEIGHT / 2.0;
+    for (i, &t) in series.times.iter().enumerate().step_by(stride) {
+        if let (Some(speed), Some(direction)) = (series.wind_speed[i], series.wind_direction[i]) {
+            wind_barb(&mut svg, x(t), wind_y, speed, direction);
+        }
+    }
+
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_format::Timestamp;
+    use resvg::usvg::{Options, Tree};
+    use serde_json::Value;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn forecast() -> Forecast {
+        let hours = 0..6;
+        let column = |f: fn(i64) -> Value| hours.clone().map(f).collect::<Vec<_>>();
+        Forecast {
+            name: "10865".to_owned(),
+            description: "MÜNCHEN & <STADT>".to_owned(),
+            issuer: String::new(),
+            generating_process: String::new(),
+            issue_time: Timestamp::from_millis(0),
+            reference_models: Vec::new(),
+            coordinates: String::new(),
+            location: None,
+            data: HashMap::from([
+                (
+                    "time_steps",
+                    column(|h| Value::from(1_792_875_600_000 + h * HOUR)),
+                ),
+                ("temp", column(|h| Value::from(280.15 + h as f64))),
+                (
+                    "dew_point",
+                    column(|h| {
+                        if h == 2 {
+                            Value::Null
+                        } else {
+                            Value::from(275.15)
+                        }
+                    }),
+                ),
+                (
+                    "precipitation_1h_significant_weather",
+                    column(|h| Value::from(h as f64 * 0.4)),
+                ),
+                ("wind_speed", column(|h| Value::from(h as f64 * 6.0))),
+                ("wind_direction", column(|h| Value::from(h as f64 * 60.0))),
+                (
+                    "total_cloud_cover",
+                    column(|h| Value::from(h as f64 * 20.0)),
+                ),
+            ]),
+            units: BTreeMap::from([
+                ("temp", Unit::Kelvin),
+                ("dew_point", Unit::Kelvin),
+                (
+                    "precipitation_1h_significant_weather",
+                    Unit::KilogramsPerSquareMeter,
+                ),
+                ("wind_speed", Unit::MetersPerSecond),
+                ("wind_direction", Unit::Degrees),
+                ("total_cloud_cover", Unit::Percent),
+            ]),
+            n_data_points: 6,
+            weather: None,
+            raw: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn query(hours: u32, width: u32, height: u32) -> MeteogramQuery {
+        MeteogramQuery {
+            hours,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn steps_and_ticks() {
+        assert_eq!(nice_step(10.0, 6.0), 2.0);
+        assert_eq!(nice_step(0.3, 4.0), 0.1);
+        assert_eq!(nice_step(35.0, 5.0), 10.0);
+        // a flat line still gets a step
+        assert!(nice_step(0.0, 5.0) > 0.0);
+        assert_eq!(
+            ticks(0.0, 0.3, 0.1).collect::<Vec<_>>(),
+            [0.0, 0.1, 0.2, 0.3]
+        );
+        assert_eq!(ticks(-5.0, 5.0, 5.0).collect::<Vec<_>>(), [-5.0, 0.0, 5.0]);
+    }
+
+    #[test]
+    fn path_gaps() {
+        let points = [Some((0.0, 0.0)), Some((1.0, 1.5)), None, Some((3.0, 2.25))];
+        assert_eq!(line_path(points.into_iter()), "M0.0,0.0L1.0,1.5M3.0,2.2");
+        assert_eq!(line_path([None, None].into_iter()), "");
+    }
+
+    #[test]
+    fn wind_barbs() {
+        let barb = |knots| {
+            let mut svg = String::new();
+            wind_barb(&mut svg, 10.0, 20.0, knots, 90.0);
+            svg
+        };
+        assert!(barb(2.0).starts_with("<circle"));
+
+        // a pennant, a full barb and a half barb
+        let svg = barb(64.0);
+        assert!(svg.contains("rotate(90)"));
+        assert_eq!(svg.matches("<polygon").count(), 1);
+        assert_eq!(svg.matches(r#"x2="7""#).count(), 1);
+        assert_eq!(svg.matches(r#"x2="3.5""#).count(), 1);
+
+        // a lone half barb isn't at the tip
+        assert!(barb(5.0).contains(r#"<line x1="0" y1="-13.0" x2="3.5" y2="-14.5"/>"#));
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape(r#"<a & "b">"#), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn query_limits() {
+        for query in [
+            query(0, 800, 400),
+            query(241, 800, 400),
+            query(48, 299, 400),
+            query(48, 4001, 400),
+            query(48, 800, 199),
+            query(48, 800, 3001),
+        ] {
+            assert!(matches!(
+                render_meteogram(&forecast(), &query, UnitSystem::Metric, Tz::UTC),
+                Err(DwdError::InvalidImageOptions(_))
+            ));
+        }
+        for query in [query(1, 300, 200), query(240, 4000, 3000)] {
+            assert!(query.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn render() {
+        let svg = render_meteogram(
+            &forecast(),
+            &query(48, 800, 400),
+            UnitSystem::Metric,
+            Tz::Europe__Berlin,
+        )
+        .unwrap();
+        assert!(svg.contains("MÜNCHEN &amp; &lt;STADT&gt;"));
+        let tree = Tree::from_str(&svg, &Options::default()).unwrap();
+        assert_eq!((tree.size().width(), tree.size().height()), (800.0, 400.0));
+    }
+}
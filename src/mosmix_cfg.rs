@@ -1,22 +1,47 @@
-use crate::DwdError;
+use crate::{
+    geojson::{Point, ToFeature},
+    DwdError,
+};
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::Serialize;
 use std::str::FromStr;
+use utoipa::ToSchema;
 
 lazy_static! {
     static ref UNDEF_REGEX: Regex = Regex::new("^-*$").unwrap();
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct MosmixStation {
-    id: String,
-    icao: Option<String>,
-    name: String,
-    latitude: f32,
-    longitude: f32,
-    elevation: i32,
+    pub(crate) id: String,
+    pub(crate) icao: Option<String>,
+    pub(crate) name: String,
+    pub(crate) latitude: f32,
+    pub(crate) longitude: f32,
+    pub(crate) elevation: i32,
+}
+
+/// The catalogue's coordinates are in degrees and whole minutes (`52.28` is 52°28').
+pub fn degrees_minutes_to_decimal(value: f32) -> f64 {
+    let value = value as f64;
+    let abs = value.abs();
+    // the f32 isn't exact (52.28 is 52.279998...), so the minutes are rounded
+    let minutes = (abs.fract() * 100.0).round();
+    // rounded to avoid float noise like 52.46666666666667
+    let decimal = ((abs.trunc() + minutes / 60.0) * 1e5).round() / 1e5;
+    decimal.copysign(value)
+}
+
+impl ToFeature for MosmixStation {
+    fn geometry(&self) -> Option<Point> {
+        Some(Point::new(
+            degrees_minutes_to_decimal(self.longitude),
+            degrees_minutes_to_decimal(self.latitude),
+            Some(self.elevation as f64),
+        ))
+    }
 }
 
 pub async fn get_mosmix_stations() -> Result<Vec<MosmixStation>, DwdError> {
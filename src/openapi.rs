@@ -0,0 +1,167 @@
+use crate::{
+    batch::{BatchError, BatchItem, BatchRequest, BatchResult},
+    current::CurrentConditions,
+    daily::{DailyForecast, DailySummary},
+    diagnostics::ParseWarning,
+    elements::KeyScheme,
+    format::ResponseFormat,
+    geojson::{Feature, FeatureCollection, Point},
+    map::{MapSnapshot, MapValue},
+    mosmix_cfg::MosmixStation,
+    report_stations::ReportStation,
+    time_format::{TimeFormat, Timestamp},
+    timeline::{Timeline, TimelinePoint, TimelineSource},
+    units::{Unit, UnitSystem},
+    weather_code::WeatherCondition,
+    weather_forecast::{Coordinates, Forecast, ForecastReferenceModel},
+    weather_report::{ReportRecord, WeatherReport},
+    DwdError,
+};
+use lazy_static::lazy_static;
+use utoipa::{
+    openapi::{schema::Type, ObjectBuilder, RefOr, Schema},
+    Modify, OpenApi,
+};
+
+/// Renders `/openapi.json` with Redoc, pinned to an exact version so the page only changes with the code.
+pub const REDOC_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>DWD API</title>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+  </head>
+  <body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.jsdelivr.net/npm/redoc@2.1.5/bundles/redoc.standalone.js" crossorigin="anonymous"></script>
+  </body>
+</html>
+"#;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "DWD API",
+        description = "Forecasts (MOSMIX) and observations of the Deutscher Wetterdienst (DWD) as JSON."
+    ),
+    paths(
+        crate::handle_station,
+        crate::handle_forecast_batch,
+        crate::handle_daily,
+        crate::handle_calendar,
+        crate::handle_meteogram,
+        crate::handle_get_stations,
+        crate::handle_get_report_stations,
+        crate::handle_get_report,
+        crate::handle_report_batch,
+        crate::handle_timeline,
+        crate::handle_current,
+        crate::handle_map,
+        crate::handle_map_image,
+    ),
+    components(schemas(
+        Forecast,
+        ForecastReferenceModel,
+        Coordinates,
+        WeatherReport,
+        ReportRecord,
+        MosmixStation,
+        ReportStation,
+        DailyForecast,
+        DailySummary,
+        Timeline,
+        TimelinePoint,
+        TimelineSource,
+        CurrentConditions,
+        MapSnapshot,
+        MapValue,
+        FeatureCollection<MosmixStation>,
+        FeatureCollection<ReportStation>,
+        Feature<Forecast>,
+        Point,
+        BatchRequest,
+        BatchItem<Forecast>,
+        BatchItem<WeatherReport>,
+        BatchResult<Forecast>,
+        BatchError,
+        WeatherCondition,
+        ParseWarning,
+        Timestamp,
+        Unit,
+        DwdError,
+        ResponseFormat,
+        TimeFormat,
+        UnitSystem,
+        KeyScheme,
+    )),
+    modifiers(&GeoJsonTypes),
+    tags(
+        (name = "forecast", description = "MOSMIX forecasts"),
+        (name = "report", description = "Observations of the weather stations"),
+        (name = "stations"),
+        (name = "map", description = "One element for all stations")
+    )
+)]
+pub struct ApiDoc;
+
+/// utoipa ignores `#[serde(tag)]` on structs, so the `type` member of the GeoJSON objects is added here.
+struct GeoJsonTypes;
+
+impl Modify for GeoJsonTypes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let Some(components) = openapi.components.as_mut() else {
+            return;
+        };
+        for (name, schema) in components.schemas.iter_mut() {
+            // generic schemas are named like `Feature_Forecast`
+            let geojson_type = match name.split('_').next() {
+                Some("Point") => "Point",
+                Some("Feature") => "Feature",
+                Some("FeatureCollection" | "MapSnapshot") => "FeatureCollection",
+                _ => continue,
+            };
+            if let RefOr::T(Schema::Object(object)) = schema {
+                let tag = ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .enum_values(Some([geojson_type]));
+                object.properties.insert("type".to_owned(), tag.into());
+                object.required.push("type".to_owned());
+            }
+        }
+    }
+}
+
+lazy_static! {
+    /// The document doesn't change while the server is running.
+    pub static ref OPENAPI_JSON: String = {
+        let mut openapi = ApiDoc::openapi();
+        // utoipa would add an empty license, as there's none in Cargo.toml
+        openapi.info.license = None;
+        openapi.to_json().expect("The OpenAPI document can be serialized")
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn document() {
+        let openapi = ApiDoc::openapi();
+        let schemas = openapi.components.unwrap().schemas;
+        for name in [
+            "Forecast",
+            "ForecastReferenceModel",
+            "WeatherReport",
+            "MosmixStation",
+            "DwdError",
+        ] {
+            assert!(schemas.contains_key(name), "{name} is missing");
+        }
+
+        let json: Value = serde_json::from_str(&OPENAPI_JSON).unwrap();
+        assert!(json["openapi"].as_str().unwrap().starts_with("3.1."));
+        assert!(json["info"].get("license").is_none());
+    }
+}
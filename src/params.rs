@@ -0,0 +1,103 @@
+use crate::{
+    derived::{add_derived, DerivedQuery},
+    diagnostics::ParseQuery,
+    elements::ElementsQuery,
+    time_format::TimeQuery,
+    units::UnitsQuery,
+    weather_code::{decode_forecast_weather, WeatherCodeQuery},
+    weather_forecast::{get_forecast, Forecast},
+    weather_report::{get_weather_report, ReportRecord, WeatherReport},
+    DwdError,
+};
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use serde::de::DeserializeOwned;
+
+fn query<T: DeserializeOwned>(req: &HttpRequest) -> Result<T, actix_web::Error> {
+    Ok(web::Query::<T>::from_query(req.query_string())?.into_inner())
+}
+
+/// The query parameters of `GET /forecast/{station}`, also used for `POST /forecast/batch`.
+pub struct ForecastParams {
+    pub weather_code: WeatherCodeQuery,
+    pub derived: DerivedQuery,
+    pub time: TimeQuery,
+    pub units: UnitsQuery,
+    pub parse: ParseQuery,
+    pub elements: ElementsQuery,
+}
+
+impl FromRequest for ForecastParams {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready((|| {
+            Ok(ForecastParams {
+                weather_code: query(req)?,
+                derived: query(req)?,
+                time: query(req)?,
+                units: query(req)?,
+                parse: query(req)?,
+                elements: query(req)?,
+            })
+        })())
+    }
+}
+
+pub async fn load_forecast(station: &str, params: &ForecastParams) -> Result<Forecast, DwdError> {
+    let mut forecast = get_forecast(station, params.parse.options()).await?;
+    add_derived(
+        &mut forecast.data,
+        &mut forecast.units,
+        &params.derived.derived,
+    );
+    if params.weather_code.decode_weather {
+        forecast.weather = Some(decode_forecast_weather(&forecast.data));
+    }
+    forecast.retain_elements(&params.elements.elements)?;
+    forecast.convert_units(params.units.units);
+    forecast.rename_keys(params.elements.keys);
+    forecast.format_times(params.time.formatter());
+    Ok(forecast)
+}
+
+/// The query parameters of `GET /report/{station}`, also used for `POST /report/batch`.
+pub struct ReportParams {
+    pub weather_code: WeatherCodeQuery,
+    pub time: TimeQuery,
+    pub units: UnitsQuery,
+    pub parse: ParseQuery,
+}
+
+impl FromRequest for ReportParams {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready((|| {
+            Ok(ReportParams {
+                weather_code: query(req)?,
+                time: query(req)?,
+                units: query(req)?,
+                parse: query(req)?,
+            })
+        })())
+    }
+}
+
+pub async fn load_report(
+    station: String,
+    params: &ReportParams,
+) -> Result<WeatherReport, DwdError> {
+    let mut report = get_weather_report(station, params.parse.mode()).await?;
+    if params.weather_code.decode_weather {
+        report
+            .data
+            .iter_mut()
+            .for_each(ReportRecord::decode_weather);
+    }
+    report.convert_units(params.units.units);
+    report.format_times(params.time.formatter());
+    Ok(report)
+}
@@ -0,0 +1,111 @@
+use crate::DwdError;
+use actix_web::{HttpResponse, HttpResponseBuilder};
+use lazy_static::lazy_static;
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{fontdb::Database, Options, Tree},
+};
+use std::sync::Arc;
+
+pub const SVG: &str = "image/svg+xml";
+pub const PNG: &str = "image/png";
+
+/// Used for `sans-serif`, the first one that's installed is used.
+const SANS_SERIF_FAMILIES: &[&str] = &[
+    "DejaVu Sans",
+    "Liberation Sans",
+    "Noto Sans",
+    "Arial",
+    "Helvetica",
+];
+
+lazy_static! {
+    /// Loading the system fonts is slow, so it's only done once.
+    static ref FONTS: Arc<Database> = {
+        let mut fonts = Database::new();
+        fonts.load_system_fonts();
+        let installed = SANS_SERIF_FAMILIES.iter().find(|&&family| {
+            fonts
+                .faces()
+                .any(|face| face.families.iter().any(|(name, _)| name == family))
+        });
+        if let Some(&family) = installed {
+            fonts.set_sans_serif_family(family);
+        }
+        Arc::new(fonts)
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    /// From the path's extension (e.g. `meteogram.png`).
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "svg" => Some(ImageFormat::Svg),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+/// Rasterizes an SVG on the CPU.
+fn svg_to_png(svg: &str) -> Result<Vec<u8>, DwdError> {
+    let options = Options {
+        fontdb: FONTS.clone(),
+        ..Options::default()
+    };
+    let tree = Tree::from_str(svg, &options).map_err(|_| DwdError::InternalError)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height()).ok_or(DwdError::InternalError)?;
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|_| DwdError::InternalError)
+}
+
+/// Responds with the SVG or rasterizes it to PNG.
+pub async fn image_response(
+    mut response: HttpResponseBuilder,
+    format: ImageFormat,
+    svg: String,
+) -> Result<HttpResponse, DwdError> {
+    Ok(match format {
+        ImageFormat::Svg => response.content_type(SVG).body(svg),
+        ImageFormat::Png => {
+            // rendering is CPU bound
+            let png = actix_web::rt::task::spawn_blocking(move || svg_to_png(&svg))
+                .await
+                .map_err(|_| DwdError::InternalError)??;
+            response.content_type(PNG).body(png)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(ImageFormat::from_extension("svg"), Some(ImageFormat::Svg));
+        assert_eq!(ImageFormat::from_extension("png"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_extension("PNG"), None);
+        assert_eq!(ImageFormat::from_extension("jpg"), None);
+    }
+
+    #[test]
+    fn png_signature_and_size() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="120" height="80"><rect width="100%" height="100%" fill="#f00"/><text x="4" y="40" font-family="sans-serif">Test</text></svg>"##;
+        let png = svg_to_png(svg).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // the IHDR chunk comes first
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 120);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 80);
+
+        assert!(matches!(svg_to_png("<svg"), Err(DwdError::InternalError)));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    geojson::{Point, ToFeature},
+    mosmix_cfg::{degrees_minutes_to_decimal, get_mosmix_stations, MosmixStation},
+    DwdError,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+lazy_static! {
+    static ref REPORT_FILE_REGEX: Regex =
+        Regex::new(r#"href="([0-9A-Za-z_]{5})-BEOB\.csv""#).unwrap();
+}
+
+/// A station with observation reports. The MOSMIX properties are `None` if the station isn't in the MOSMIX catalogue.
+#[derive(Serialize, ToSchema)]
+pub struct ReportStation {
+    id: String,
+    icao: Option<String>,
+    name: Option<String>,
+    latitude: Option<f32>,
+    longitude: Option<f32>,
+    elevation: Option<i32>,
+}
+
+impl ToFeature for ReportStation {
+    fn geometry(&self) -> Option<Point> {
+        Some(Point::new(
+            degrees_minutes_to_decimal(self.longitude?),
+            degrees_minutes_to_decimal(self.latitude?),
+            self.elevation.map(f64::from),
+        ))
+    }
+}
+
+pub async fn get_report_stations() -> Result<Vec<ReportStation>, DwdError> {
+    let (listing, mosmix) = tokio::try_join!(get_report_listing(), get_mosmix_stations())?;
+    Ok(join_report_stations(&listing, mosmix))
+}
+
+async fn get_report_listing() -> Result<String, DwdError> {
+    reqwest::get("https://opendata.dwd.de/weather/weather_reports/poi/")
+        .await
+        .map_err(|_| DwdError::NoReportListing)?
+        .error_for_status()
+        .map_err(|_| DwdError::NoReportListing)?
+        .text()
+        .await
+        .map_err(|_| DwdError::NoReportListing)
+}
+
+/// Parses the ids of the `{id}-BEOB.csv` files in the directory listing.
+/// Ids are padded to five characters with underscores in the file names, these are removed.
+pub fn parse_report_listing(listing: &str) -> Vec<String> {
+    let mut ids: Vec<String> = REPORT_FILE_REGEX
+        .captures_iter(listing)
+        .map(|c| c[1].trim_end_matches('_').to_owned())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+pub fn join_report_stations(listing: &str, mosmix: Vec<MosmixStation>) -> Vec<ReportStation> {
+    let mut mosmix: HashMap<String, MosmixStation> =
+        mosmix.into_iter().map(|s| (s.id.clone(), s)).collect();
+    parse_report_listing(listing)
+        .into_iter()
+        .map(|id| match mosmix.remove(&id) {
+            Some(station) => ReportStation {
+                id,
+                icao: station.icao,
+                name: Some(station.name),
+                latitude: Some(station.latitude),
+                longitude: Some(station.longitude),
+                elevation: Some(station.elevation),
+            },
+            None => ReportStation {
+                id,
+                icao: None,
+                name: None,
+                latitude: None,
+                longitude: None,
+                elevation: None,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING: &str = r#"<html><head><title>Index of /weather/weather_reports/poi/</title></head><body>
+<h1>Index of /weather/weather_reports/poi/</h1><hr><pre><a href="../">../</a>
+<a href="10865-BEOB.csv">10865-BEOB.csv</a>                                     24-Oct-2026 21:05     25112
+<a href="P0489-BEOB.csv">P0489-BEOB.csv</a>                                     24-Oct-2026 21:05     24870
+<a href="A172_-BEOB.csv">A172_-BEOB.csv</a>                                     24-Oct-2026 21:04     23955
+<a href="10865-BEOB.csv">10865-BEOB.csv</a>                                     24-Oct-2026 21:05     25112
+<a href="10865-BEOB.csv.gz">10865-BEOB.csv.gz</a>                               24-Oct-2026 21:05      4015
+<a href="1038-BEOB.csv">1038-BEOB.csv</a>                                       24-Oct-2026 21:05     25001
+<a href="poi_stations.txt">poi_stations.txt</a>                                 24-Oct-2026 06:00      1024
+</pre><hr></body></html>"#;
+
+    fn mosmix_station(id: &str, latitude: f32, longitude: f32) -> MosmixStation {
+        MosmixStation {
+            id: id.to_owned(),
+            icao: None,
+            name: format!("STATION {id}"),
+            latitude,
+            longitude,
+            elevation: 100,
+        }
+    }
+
+    #[test]
+    fn listing_hrefs() {
+        assert_eq!(parse_report_listing(LISTING), ["10865", "A172", "P0489"]);
+        assert!(parse_report_listing("").is_empty());
+    }
+
+    #[test]
+    fn join_with_mosmix() {
+        let mosmix = vec![
+            mosmix_station("10865", 48.10, 11.33),
+            // not in the listing
+            mosmix_station("10384", 52.28, 13.24),
+        ];
+        let stations = join_report_stations(LISTING, mosmix);
+
+        let ids: Vec<&str> = stations.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["10865", "A172", "P0489"]);
+        let munich = &stations[0];
+        assert_eq!(munich.name.as_deref(), Some("STATION 10865"));
+        assert_eq!(
+            munich.geometry().unwrap().coordinates,
+            [11.55, 48.16667, 100.0]
+        );
+        // not in the MOSMIX catalogue
+        let unknown = &stations[1];
+        assert!(unknown.name.is_none() && unknown.latitude.is_none());
+        assert!(unknown.geometry().is_none());
+    }
+
+    #[test]
+    fn degrees_minutes() {
+        assert_eq!(degrees_minutes_to_decimal(52.28), 52.46667);
+        assert_eq!(degrees_minutes_to_decimal(-52.28), -52.46667);
+        assert_eq!(degrees_minutes_to_decimal(-0.30), -0.5);
+        assert_eq!(degrees_minutes_to_decimal(-70.45), -70.75);
+        assert_eq!(degrees_minutes_to_decimal(0.0), 0.0);
+    }
+}
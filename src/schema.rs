@@ -0,0 +1,180 @@
+use crate::openapi::ApiDoc;
+use lazy_static::lazy_static;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use utoipa::OpenApi;
+
+pub const SCHEMA_JSON: &str = "application/schema+json";
+
+/// The version of the API, schemas change with it (see `X-DWD-API-Version`).
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The response (body) schemas by their name in `/schema/{name}.json`, the definition they refer to
+/// and whether the response is an array of it.
+const RESPONSES: &[(&str, &str, bool)] = &[
+    ("forecast", "Forecast", false),
+    ("forecast_geojson", "Feature_Forecast", false),
+    ("forecast_batch", "BatchItem_Forecast", true),
+    ("daily", "DailyForecast", false),
+    ("stations", "MosmixStation", true),
+    ("stations_geojson", "FeatureCollection_MosmixStation", false),
+    ("report", "WeatherReport", false),
+    ("report_batch", "BatchItem_WeatherReport", true),
+    ("report_stations", "ReportStation", true),
+    (
+        "report_stations_geojson",
+        "FeatureCollection_ReportStation",
+        false,
+    ),
+    ("timeline", "Timeline", false),
+    ("current", "CurrentConditions", false),
+    ("map", "MapSnapshot", false),
+    ("error", "DwdError", false),
+];
+
+lazy_static! {
+    /// The serialized schemas by name, they don't change while the server is running.
+    pub static ref SCHEMAS: HashMap<&'static str, String> = build_schemas();
+}
+
+/// The names of the available schemas and the version they belong to.
+pub fn schema_index() -> Value {
+    let schemas: BTreeMap<&str, String> = RESPONSES
+        .iter()
+        .map(|(name, _, _)| (*name, format!("/schema/{name}.json")))
+        .collect();
+    json!({ "version": VERSION, "schemas": schemas })
+}
+
+/// Standalone JSON Schemas (2020-12) derived from the components of the OpenAPI document.
+/// Each one only contains the definitions it references.
+fn build_schemas() -> HashMap<&'static str, String> {
+    let openapi = ApiDoc::openapi();
+    let components = openapi
+        .components
+        .map(|components| components.schemas)
+        .unwrap_or_default();
+    let mut definitions: HashMap<String, Value> = components
+        .into_iter()
+        .filter_map(|(name, schema)| Some((name, serde_json::to_value(schema).ok()?)))
+        .collect();
+    definitions.values_mut().for_each(rewrite_refs);
+
+    RESPONSES
+        .iter()
+        .map(|&(name, definition, is_array)| {
+            let reference = format!("#/$defs/{definition}");
+            let mut schema = if is_array {
+                json!({ "type": "array", "items": { "$ref": reference } })
+            } else {
+                json!({ "$ref": reference })
+            };
+            schema["$schema"] = "https://json-schema.org/draft/2020-12/schema".into();
+            schema["title"] = name.into();
+            // an annotation, unknown keywords are ignored by validators
+            schema["version"] = VERSION.into();
+            schema["$defs"] = Value::Object(referenced(definition, &definitions));
+            (name, schema.to_string())
+        })
+        .collect()
+}
+
+/// OpenAPI references components, a standalone schema its `$defs`.
+fn rewrite_refs(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::String(reference)) = object.get_mut("$ref") {
+                *reference = reference.replace("#/components/schemas/", "#/$defs/");
+            }
+            object.values_mut().for_each(rewrite_refs);
+        }
+        Value::Array(values) => values.iter_mut().for_each(rewrite_refs),
+        _ => {}
+    }
+}
+
+/// `root` and every definition it (transitively) references.
+fn referenced(root: &str, definitions: &HashMap<String, Value>) -> Map<String, Value> {
+    let mut found = Map::new();
+    let mut pending = vec![root.to_owned()];
+    while let Some(name) = pending.pop() {
+        if found.contains_key(&name) {
+            continue;
+        }
+        debug_assert!(
+            definitions.contains_key(&name),
+            "{name} is referenced but isn't a component"
+        );
+        let Some(definition) = definitions.get(&name) else {
+            continue;
+        };
+        collect_refs(definition, &mut pending);
+        found.insert(name, definition.clone());
+    }
+    found
+}
+
+fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(name) = object
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix("#/$defs/"))
+            {
+                refs.push(name.to_owned());
+            }
+            object.values().for_each(|value| collect_refs(value, refs));
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_refs(value, refs)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elements::all_element_keys;
+
+    fn schema(name: &str) -> Value {
+        serde_json::from_str(&SCHEMAS[name]).unwrap()
+    }
+
+    #[test]
+    fn refs_resolve_within_their_schema() {
+        for &(name, definition, _) in RESPONSES {
+            let schema = schema(name);
+            let definitions = schema["$defs"].as_object().unwrap();
+            assert!(definitions.contains_key(definition), "{name}");
+            let mut refs = Vec::new();
+            collect_refs(&schema, &mut refs);
+            assert!(!refs.is_empty(), "{name}");
+            for reference in refs {
+                assert!(
+                    definitions.contains_key(&reference),
+                    "{name} references {reference}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing is referenced but isn't a component")]
+    fn missing_definitions_fail() {
+        let definitions =
+            HashMap::from([("Root".to_owned(), json!({ "$ref": "#/$defs/Missing" }))]);
+        referenced("Root", &definitions);
+    }
+
+    #[test]
+    fn forecast_lists_every_element() {
+        let schema = schema("forecast");
+        let forecast = &schema["$defs"]["Forecast"]["properties"];
+        for field in ["data", "units"] {
+            let properties = forecast[field]["properties"].as_object().unwrap();
+            for key in all_element_keys() {
+                assert!(properties.contains_key(key), "{field} is missing {key}");
+            }
+        }
+    }
+}
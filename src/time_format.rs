@@ -0,0 +1,134 @@
+use chrono::{DateTime, SecondsFormat};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use utoipa::{
+    openapi::{
+        schema::{KnownFormat, SchemaFormat, Type},
+        ObjectBuilder, OneOfBuilder, RefOr, Schema,
+    },
+    IntoParams, PartialSchema, ToSchema,
+};
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimeQuery {
+    #[serde(default)]
+    pub time_format: TimeFormat,
+    /// An IANA timezone (e.g. `Europe/Berlin`)
+    #[param(value_type = Option<String>)]
+    pub tz: Option<Tz>,
+}
+
+impl TimeQuery {
+    /// Formats timestamps in `tz` or UTC if none was requested.
+    pub fn formatter(&self) -> TimeFormatter {
+        self.formatter_or(Tz::UTC)
+    }
+
+    pub fn formatter_or(&self, default_tz: Tz) -> TimeFormatter {
+        TimeFormatter {
+            format: self.time_format,
+            tz: self.tz.unwrap_or(default_tz),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeFormat {
+    Iso8601,
+    #[default]
+    EpochMs,
+    EpochS,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimeFormatter {
+    pub format: TimeFormat,
+    pub tz: Tz,
+}
+
+impl Default for TimeFormatter {
+    fn default() -> Self {
+        Self {
+            format: TimeFormat::EpochMs,
+            tz: Tz::UTC,
+        }
+    }
+}
+
+impl TimeFormatter {
+    pub fn format(&self, millis: i64) -> Value {
+        match self.format {
+            TimeFormat::EpochMs => Value::from(millis),
+            TimeFormat::EpochS => Value::from(millis.div_euclid(1000)),
+            TimeFormat::Iso8601 => self.iso8601(millis).map(Value::from).unwrap_or(Value::Null),
+        }
+    }
+
+    /// Reformats a timestamp that's currently stored as epoch milliseconds.
+    pub fn format_value(&self, value: &mut Value) {
+        if let Some(millis) = value.as_i64() {
+            *value = self.format(millis);
+        }
+    }
+
+    /// ISO 8601 with the offset that's valid at that instant in `tz` (e.g. `+02:00` during CEST).
+    fn iso8601(&self, millis: i64) -> Option<String> {
+        DateTime::from_timestamp_millis(millis).map(|t| {
+            t.with_timezone(&self.tz)
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+        })
+    }
+}
+
+/// A UTC instant that is serialized according to its [`TimeFormatter`] (epoch milliseconds by default).
+#[derive(Debug, Clone, Copy)]
+pub struct Timestamp {
+    millis: i64,
+    formatter: TimeFormatter,
+}
+
+impl Timestamp {
+    pub fn from_millis(millis: i64) -> Self {
+        Self {
+            millis,
+            formatter: TimeFormatter::default(),
+        }
+    }
+
+    pub fn millis(&self) -> i64 {
+        self.millis
+    }
+
+    pub fn format_with(&mut self, formatter: TimeFormatter) {
+        self.formatter = formatter;
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.formatter.format(self.millis).serialize(serializer)
+    }
+}
+
+/// Epoch milliseconds or seconds, or an ISO 8601 string, depending on `time_format`.
+impl PartialSchema for Timestamp {
+    fn schema() -> RefOr<Schema> {
+        OneOfBuilder::new()
+            .item(
+                ObjectBuilder::new()
+                    .schema_type(Type::Integer)
+                    .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64))),
+            )
+            .item(
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .format(Some(SchemaFormat::KnownFormat(KnownFormat::DateTime))),
+            )
+            .into()
+    }
+}
+
+impl ToSchema for Timestamp {}
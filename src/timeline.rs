@@ -0,0 +1,277 @@
+use crate::{
+    diagnostics::{ParseOptions, ParseWarning},
+    time_format::{TimeFormatter, Timestamp},
+    units::{Unit, UnitSystem},
+    weather_forecast::{get_forecast, Forecast},
+    weather_report::{get_weather_report, ReportParameter, WeatherReport},
+    DwdError,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::{
+    openapi::{
+        schema::{AdditionalProperties, Type},
+        Object, ObjectBuilder, Ref, RefOr, Schema,
+    },
+    ToSchema,
+};
+
+/// How many milliseconds of observations are included before the forecast.
+const OBSERVATION_WINDOW: i64 = 24 * 60 * 60 * 1000;
+
+/// Parameters that are both observed and forecast: (key, report parameter, forecast key).
+/// The keys are the ones used in the report.
+const TIMELINE_PARAMETERS: &[(&str, ReportParameter, &str)] = &[
+    ("temp", ReportParameter::Temp, "temp"),
+    ("dew_point", ReportParameter::DewPoint, "dew_point"),
+    ("wind_speed", ReportParameter::WindSpeed, "wind_speed"),
+    (
+        "wind_direction",
+        ReportParameter::WindDirection,
+        "wind_direction",
+    ),
+    (
+        "max_wind_gust_1h",
+        ReportParameter::MaxWindGust1h,
+        "max_wind_gust_1h",
+    ),
+    (
+        "precipitation_1h",
+        ReportParameter::Precipitation1h,
+        "precipitation_1h_significant_weather",
+    ),
+    (
+        "total_cloud_cover",
+        ReportParameter::TotalCloudCover,
+        "total_cloud_cover",
+    ),
+    ("visibility", ReportParameter::Visibility, "visibility"),
+    (
+        "surface_pressure",
+        ReportParameter::SurfacePressure,
+        "surface_pressure",
+    ),
+    (
+        "sunshine_duration_1h",
+        ReportParameter::SunshineDuration1h,
+        "sunshine_last_hour",
+    ),
+    (
+        "global_radiation_1h",
+        ReportParameter::GlobalRadiation1h,
+        "global_irradiance",
+    ),
+    (
+        "present_weather",
+        ReportParameter::PresentWeather,
+        "significant_weather",
+    ),
+];
+
+#[derive(Serialize, ToSchema)]
+pub struct Timeline {
+    pub name: String,
+    pub description: String,
+    #[schema(schema_with = units_schema)]
+    pub units: BTreeMap<&'static str, Unit>,
+    pub data: Vec<TimelinePoint>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineSource {
+    Observed,
+    Forecast,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TimelinePoint {
+    pub timestamp: Timestamp,
+    pub source: TimelineSource,
+    #[serde(flatten)]
+    #[schema(schema_with = point_values_schema)]
+    pub values: BTreeMap<&'static str, f64>,
+}
+
+/// An object with a `schema` for each of the [`TIMELINE_PARAMETERS`] keys, without other keys if `closed`.
+pub fn keyed_schema(schema: impl Into<RefOr<Schema>> + Clone, closed: bool) -> ObjectBuilder {
+    TIMELINE_PARAMETERS
+        .iter()
+        .fold(ObjectBuilder::new(), |object, (key, _, _)| {
+            object.property(*key, schema.clone())
+        })
+        .additional_properties(Some(AdditionalProperties::FreeForm(!closed)))
+}
+
+pub fn units_schema() -> Object {
+    keyed_schema(Ref::from_schema_name("Unit"), true).build()
+}
+
+pub fn values_schema() -> Object {
+    keyed_schema(ObjectBuilder::new().schema_type(Type::Number).build(), true).build()
+}
+
+/// Open, the values are flattened next to `timestamp` and `source`.
+fn point_values_schema() -> Object {
+    keyed_schema(
+        ObjectBuilder::new().schema_type(Type::Number).build(),
+        false,
+    )
+    .build()
+}
+
+pub async fn get_timeline(
+    station: &str,
+    options: ParseOptions,
+    system: UnitSystem,
+) -> Result<Timeline, DwdError> {
+    let (report, forecast) = tokio::join!(
+        get_weather_report(station.to_owned(), options.mode),
+        get_forecast(station, options)
+    );
+    // not every MOSMIX station has reports, the timeline is only the forecast then
+    let report = match report {
+        Err(DwdError::NoReport) => WeatherReport::default(),
+        report => report?,
+    };
+    Ok(merge_timeline(report, forecast?, system))
+}
+
+/// The observations and forecast time steps with the same keys and units.
+pub struct Harmonized {
+    pub units: BTreeMap<&'static str, Unit>,
+    /// Sorted by time
+    pub observed: Vec<TimelinePoint>,
+    /// Sorted by time
+    pub forecast: Vec<TimelinePoint>,
+}
+
+/// Converts the [`TIMELINE_PARAMETERS`] of both sources to `system` (metric if it's [`UnitSystem::Raw`]).
+pub fn harmonize(report: &WeatherReport, forecast: &Forecast, system: UnitSystem) -> Harmonized {
+    let system = match system {
+        UnitSystem::Raw => UnitSystem::Metric,
+        system => system,
+    };
+
+    let mut units = BTreeMap::new();
+    let mut report_units = Vec::new();
+    let mut forecast_units = Vec::new();
+    for &(key, param, forecast_key) in TIMELINE_PARAMETERS {
+        let quantity = param.quantity();
+        let from_report = report.units.get(&param).copied();
+        let from_forecast = forecast.units.get(forecast_key).copied();
+        let Some(target) = system.target(quantity).or(from_forecast).or(from_report) else {
+            continue;
+        };
+        units.insert(key, target);
+        report_units.push((key, param, from_report, target, quantity));
+        forecast_units.push((key, forecast_key, from_forecast, target, quantity));
+    }
+
+    let mut observed: Vec<TimelinePoint> = report
+        .data
+        .iter()
+        .map(|record| TimelinePoint {
+            timestamp: record.timestamp,
+            source: TimelineSource::Observed,
+            values: report_units
+                .iter()
+                .filter_map(|&(key, param, from, to, quantity)| {
+                    let value = *record.values.get(&param)?;
+                    Some((key, from?.convert_quantity(value, to, quantity)?))
+                })
+                .collect(),
+        })
+        .collect();
+    observed.sort_by_key(|point| point.timestamp.millis());
+
+    let columns: Vec<_> = forecast_units
+        .iter()
+        .map(|&(key, forecast_key, from, to, quantity)| {
+            (key, forecast.column(forecast_key), from, to, quantity)
+        })
+        .collect();
+    let mut forecast: Vec<TimelinePoint> = forecast
+        .column("time_steps")
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, time)| {
+            Some(TimelinePoint {
+                timestamp: Timestamp::from_millis(time? as i64),
+                source: TimelineSource::Forecast,
+                values: columns
+                    .iter()
+                    .filter_map(|&(key, ref column, from, to, quantity)| {
+                        Some((key, from?.convert_quantity(column[i]?, to, quantity)?))
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+    forecast.sort_by_key(|point| point.timestamp.millis());
+
+    Harmonized {
+        units,
+        observed,
+        forecast,
+    }
+}
+
+/// Merges the last 24h of observations with the forecast following the latest observation.
+pub fn merge_timeline(report: WeatherReport, forecast: Forecast, system: UnitSystem) -> Timeline {
+    let harmonized = harmonize(&report, &forecast, system);
+
+    let latest_observation = harmonized.observed.last().map(|p| p.timestamp.millis());
+    let data = harmonized
+        .observed
+        .into_iter()
+        .filter(|point| {
+            latest_observation
+                .is_some_and(|latest| point.timestamp.millis() > latest - OBSERVATION_WINDOW)
+        })
+        .chain(harmonized.forecast.into_iter().filter(|point| {
+            latest_observation.is_none_or(|latest| point.timestamp.millis() > latest)
+        }))
+        .collect();
+
+    let mut warnings = report.warnings;
+    warnings.extend(forecast.warnings);
+    Timeline {
+        name: forecast.name,
+        description: forecast.description,
+        units: harmonized.units,
+        data,
+        warnings,
+    }
+}
+
+impl Timeline {
+    pub fn format_times(&mut self, formatter: TimeFormatter) {
+        for point in &mut self.data {
+            point.timestamp.format_with(formatter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radiation_has_the_same_unit_in_both_sources() {
+        let (_, param, forecast_key) = TIMELINE_PARAMETERS
+            .iter()
+            .find(|(key, _, _)| *key == "global_radiation_1h")
+            .unwrap();
+        let forecast_element = crate::elements::element_by_key(forecast_key).unwrap();
+        assert_eq!(forecast_element.quantity, param.quantity());
+        for system in [UnitSystem::Si, UnitSystem::Metric, UnitSystem::Imperial] {
+            let target = system.target(param.quantity()).unwrap();
+            let converted = forecast_element
+                .unit
+                .convert_quantity(1.0, target, param.quantity());
+            assert!(converted.is_some());
+        }
+    }
+}
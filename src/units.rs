@@ -0,0 +1,448 @@
+use serde::{Deserialize, Serialize, Serializer};
+use utoipa::{
+    openapi::{schema::Type, ObjectBuilder, RefOr, Schema},
+    IntoParams, PartialSchema, ToSchema,
+};
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UnitsQuery {
+    #[serde(default)]
+    pub units: UnitSystem,
+}
+
+/// The units values are converted to.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    /// Units as delivered by the DWD
+    #[default]
+    Raw,
+    Si,
+    Metric,
+    Imperial,
+}
+
+/// What a value describes. This decides the unit a value is converted to in a [`UnitSystem`]
+/// (e.g. a visibility and a snow depth are both lengths, but are shown in km and cm).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    Temperature,
+    /// Temperature errors/differences use the temperature units, but only their scale is converted
+    /// (2 K are 2 °C and 3.6 °F)
+    TemperatureDifference,
+    Speed,
+    Direction,
+    Pressure,
+    Precipitation,
+    SnowDepth,
+    Height,
+    Visibility,
+    Duration,
+    /// An energy sum over any period (kJ/m2)
+    Radiation,
+    /// Radiation during one hour, either as sum (kJ/m2) or as mean power (W/m2)
+    Irradiance,
+    Percent,
+    Code,
+}
+
+impl UnitSystem {
+    pub fn target(self, quantity: Quantity) -> Option<Unit> {
+        use self::{Quantity as Q, Unit as U, UnitSystem as S};
+        let unit = match (self, quantity) {
+            (S::Raw, _) => return None,
+            (S::Si, Q::Temperature | Q::TemperatureDifference) => U::Kelvin,
+            (S::Metric, Q::Temperature | Q::TemperatureDifference) => U::Celsius,
+            (S::Imperial, Q::Temperature | Q::TemperatureDifference) => U::Fahrenheit,
+            (S::Si, Q::Speed) => U::MetersPerSecond,
+            (S::Metric, Q::Speed) => U::KilometersPerHour,
+            (S::Imperial, Q::Speed) => U::MilesPerHour,
+            (S::Si, Q::Pressure) => U::Pascal,
+            (S::Metric, Q::Pressure) => U::Hectopascal,
+            (S::Imperial, Q::Pressure) => U::InchesOfMercury,
+            (S::Si, Q::Precipitation) => U::KilogramsPerSquareMeter,
+            (S::Metric, Q::Precipitation) => U::Millimeters,
+            (S::Imperial, Q::Precipitation) => U::Inches,
+            (S::Si, Q::SnowDepth) => U::Meters,
+            (S::Metric, Q::SnowDepth) => U::Centimeters,
+            (S::Imperial, Q::SnowDepth) => U::Inches,
+            (S::Si | S::Metric, Q::Height) => U::Meters,
+            (S::Imperial, Q::Height) => U::Feet,
+            (S::Si, Q::Visibility) => U::Meters,
+            (S::Metric, Q::Visibility) => U::Kilometers,
+            (S::Imperial, Q::Visibility) => U::Miles,
+            (S::Si, Q::Duration) => U::Seconds,
+            (S::Metric | S::Imperial, Q::Duration) => U::Minutes,
+            (_, Q::Radiation) => U::KilojoulesPerSquareMeter,
+            (_, Q::Irradiance) => U::WattsPerSquareMeter,
+            (_, Q::Direction | Q::Percent | Q::Code) => return None,
+        };
+        Some(unit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Kelvin,
+    Celsius,
+    Fahrenheit,
+    MetersPerSecond,
+    KilometersPerHour,
+    Knots,
+    MilesPerHour,
+    Pascal,
+    Hectopascal,
+    InchesOfMercury,
+    KilogramsPerSquareMeter,
+    Millimeters,
+    Centimeters,
+    Meters,
+    Kilometers,
+    Inches,
+    Feet,
+    Miles,
+    Seconds,
+    Minutes,
+    Hours,
+    KilojoulesPerSquareMeter,
+    WattsPerSquareMeter,
+    Percent,
+    Degrees,
+    Beaufort,
+    Code,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Temperature,
+    Speed,
+    Pressure,
+    Length,
+    Duration,
+    Radiation,
+    Irradiance,
+    None,
+}
+
+impl Unit {
+    const ALL: &'static [Unit] = &[
+        Unit::Kelvin,
+        Unit::Celsius,
+        Unit::Fahrenheit,
+        Unit::MetersPerSecond,
+        Unit::KilometersPerHour,
+        Unit::Knots,
+        Unit::MilesPerHour,
+        Unit::Pascal,
+        Unit::Hectopascal,
+        Unit::InchesOfMercury,
+        Unit::KilogramsPerSquareMeter,
+        Unit::Millimeters,
+        Unit::Centimeters,
+        Unit::Meters,
+        Unit::Kilometers,
+        Unit::Inches,
+        Unit::Feet,
+        Unit::Miles,
+        Unit::Seconds,
+        Unit::Minutes,
+        Unit::Hours,
+        Unit::KilojoulesPerSquareMeter,
+        Unit::WattsPerSquareMeter,
+        Unit::Percent,
+        Unit::Degrees,
+        Unit::Beaufort,
+        Unit::Code,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Kelvin => "K",
+            Unit::Celsius => "°C",
+            Unit::Fahrenheit => "°F",
+            Unit::MetersPerSecond => "m/s",
+            Unit::KilometersPerHour => "km/h",
+            Unit::Knots => "kn",
+            Unit::MilesPerHour => "mph",
+            Unit::Pascal => "Pa",
+            Unit::Hectopascal => "hPa",
+            Unit::InchesOfMercury => "inHg",
+            Unit::KilogramsPerSquareMeter => "kg/m2",
+            Unit::Millimeters => "mm",
+            Unit::Centimeters => "cm",
+            Unit::Meters => "m",
+            Unit::Kilometers => "km",
+            Unit::Inches => "in",
+            Unit::Feet => "ft",
+            Unit::Miles => "mi",
+            Unit::Seconds => "s",
+            Unit::Minutes => "min",
+            Unit::Hours => "h",
+            Unit::KilojoulesPerSquareMeter => "kJ/m2",
+            Unit::WattsPerSquareMeter => "W/m2",
+            Unit::Percent => "%",
+            Unit::Degrees => "°",
+            Unit::Beaufort => "Bft",
+            Unit::Code => "-",
+        }
+    }
+
+    /// Parses the unit labels used by the DWD, which aren't consistent (e.g. `Grad C` and `°C`).
+    pub fn parse(label: &str) -> Option<Unit> {
+        let normalized: String = label
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase()
+            .replace('²', "2");
+        let unit = match normalized.as_str() {
+            "k" => Unit::Kelvin,
+            "°c" | "gradc" | "degc" | "c" => Unit::Celsius,
+            "°f" | "degf" | "f" => Unit::Fahrenheit,
+            "m/s" => Unit::MetersPerSecond,
+            "km/h" | "kmh" => Unit::KilometersPerHour,
+            "kn" | "kt" => Unit::Knots,
+            "mph" => Unit::MilesPerHour,
+            "pa" => Unit::Pascal,
+            "hpa" => Unit::Hectopascal,
+            "inhg" => Unit::InchesOfMercury,
+            "kg/m2" => Unit::KilogramsPerSquareMeter,
+            "mm" => Unit::Millimeters,
+            "cm" => Unit::Centimeters,
+            "m" => Unit::Meters,
+            "km" => Unit::Kilometers,
+            "in" => Unit::Inches,
+            "ft" => Unit::Feet,
+            "mi" => Unit::Miles,
+            "s" => Unit::Seconds,
+            "min" => Unit::Minutes,
+            "h" => Unit::Hours,
+            "kj/m2" => Unit::KilojoulesPerSquareMeter,
+            "w/m2" => Unit::WattsPerSquareMeter,
+            "%" => Unit::Percent,
+            "°" | "grad" | "deg" => Unit::Degrees,
+            "bft" => Unit::Beaufort,
+            "code_table" | "-" => Unit::Code,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// The dimension and the factor and offset to its base unit (`base = value * factor + offset`).
+    fn base(self) -> (Dimension, f64, f64) {
+        match self {
+            Unit::Kelvin => (Dimension::Temperature, 1.0, 0.0),
+            Unit::Celsius => (Dimension::Temperature, 1.0, 273.15),
+            Unit::Fahrenheit => (Dimension::Temperature, 5.0 / 9.0, 273.15 - 32.0 * 5.0 / 9.0),
+            Unit::MetersPerSecond => (Dimension::Speed, 1.0, 0.0),
+            Unit::KilometersPerHour => (Dimension::Speed, 1.0 / 3.6, 0.0),
+            Unit::Knots => (Dimension::Speed, 1852.0 / 3600.0, 0.0),
+            Unit::MilesPerHour => (Dimension::Speed, 0.44704, 0.0),
+            Unit::Pascal => (Dimension::Pressure, 1.0, 0.0),
+            Unit::Hectopascal => (Dimension::Pressure, 100.0, 0.0),
+            Unit::InchesOfMercury => (Dimension::Pressure, 3386.389, 0.0),
+            // 1 kg/m2 of water is 1 mm
+            Unit::KilogramsPerSquareMeter | Unit::Millimeters => (Dimension::Length, 0.001, 0.0),
+            Unit::Centimeters => (Dimension::Length, 0.01, 0.0),
+            Unit::Meters => (Dimension::Length, 1.0, 0.0),
+            Unit::Kilometers => (Dimension::Length, 1000.0, 0.0),
+            Unit::Inches => (Dimension::Length, 0.0254, 0.0),
+            Unit::Feet => (Dimension::Length, 0.3048, 0.0),
+            Unit::Miles => (Dimension::Length, 1609.344, 0.0),
+            Unit::Seconds => (Dimension::Duration, 1.0, 0.0),
+            Unit::Minutes => (Dimension::Duration, 60.0, 0.0),
+            Unit::Hours => (Dimension::Duration, 3600.0, 0.0),
+            Unit::KilojoulesPerSquareMeter => (Dimension::Radiation, 1.0, 0.0),
+            Unit::WattsPerSquareMeter => (Dimension::Irradiance, 1.0, 0.0),
+            Unit::Percent | Unit::Degrees | Unit::Beaufort | Unit::Code => {
+                (Dimension::None, 1.0, 0.0)
+            }
+        }
+    }
+
+    /// Converts a value from `self` to `to`. Returns `None` if the units are incompatible.
+    /// Converted values are rounded to four decimal places to avoid values like `49.999999999999986`.
+    pub fn convert(self, value: f64, to: Unit) -> Option<f64> {
+        if self == to {
+            return Some(value);
+        }
+        let (from_dim, from_factor, from_offset) = self.base();
+        let (to_dim, to_factor, to_offset) = to.base();
+        if from_dim != to_dim || from_dim == Dimension::None {
+            return None;
+        }
+        let converted = (value * from_factor + from_offset - to_offset) / to_factor;
+        Some(round(converted))
+    }
+
+    /// Like [`Unit::convert`], but differences ([`Quantity::TemperatureDifference`]) aren't shifted by the
+    /// offset between the units and an hour of radiation ([`Quantity::Irradiance`]) can be converted
+    /// between its sum and its mean power.
+    pub fn convert_quantity(self, value: f64, to: Unit, quantity: Quantity) -> Option<f64> {
+        use self::Unit::{KilojoulesPerSquareMeter as Kj, WattsPerSquareMeter as W};
+        match (quantity, self, to) {
+            (Quantity::TemperatureDifference, _, _) => {
+                Some(round(self.convert(value, to)? - self.convert(0.0, to)?))
+            }
+            // 1 W/m2 during 3600 s are 3.6 kJ/m2
+            (Quantity::Irradiance, Kj, W) => Some(round(value / 3.6)),
+            (Quantity::Irradiance, W, Kj) => Some(round(value * 3.6)),
+            _ => self.convert(value, to),
+        }
+    }
+}
+
+/// Converted values are rounded to four decimal places.
+pub fn round(value: f64) -> f64 {
+    (value * 10_000.0).round() / 10_000.0
+}
+
+impl Serialize for Unit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.symbol())
+    }
+}
+
+/// Units are serialized as their symbol.
+impl PartialSchema for Unit {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .enum_values(Some(Unit::ALL.iter().map(|unit| unit.symbol())))
+            .into()
+    }
+}
+
+impl ToSchema for Unit {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A quantity and the unit the DWD delivers it in.
+    const DELIVERED: &[(Quantity, Unit)] = &[
+        (Quantity::Temperature, Unit::Kelvin),
+        (Quantity::TemperatureDifference, Unit::Kelvin),
+        (Quantity::Speed, Unit::MetersPerSecond),
+        (Quantity::Pressure, Unit::Pascal),
+        (Quantity::Precipitation, Unit::KilogramsPerSquareMeter),
+        (Quantity::SnowDepth, Unit::Meters),
+        (Quantity::Height, Unit::Meters),
+        (Quantity::Visibility, Unit::Meters),
+        (Quantity::Duration, Unit::Seconds),
+        (Quantity::Radiation, Unit::KilojoulesPerSquareMeter),
+        (Quantity::Irradiance, Unit::KilojoulesPerSquareMeter),
+        (Quantity::Irradiance, Unit::WattsPerSquareMeter),
+    ];
+
+    #[test]
+    fn round_trip_every_quantity() {
+        for system in [UnitSystem::Si, UnitSystem::Metric, UnitSystem::Imperial] {
+            for &(quantity, unit) in DELIVERED {
+                let target = system.target(quantity).unwrap();
+                // values are rounded to four decimal places of the target unit (e.g. 0.1 m in km)
+                let to_unit = |value| target.convert_quantity(value, unit, quantity).unwrap();
+                let scale = to_unit(1.0) - to_unit(0.0);
+                let tolerance = (scale.abs() * 1e-4).max(1e-4) + 1e-9;
+                for value in [0.0, 1.5, 287.35, 10250.0] {
+                    let converted = unit.convert_quantity(value, target, quantity).unwrap();
+                    let back = target.convert_quantity(converted, unit, quantity).unwrap();
+                    assert!(
+                        (back - value).abs() <= tolerance,
+                        "{quantity:?} {value} {unit:?} -> {converted} {target:?} -> {back}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reference_values() {
+        let convert = |value, from: Unit, to| from.convert(value, to).unwrap();
+        assert_eq!(convert(273.15, Unit::Kelvin, Unit::Celsius), 0.0);
+        assert_eq!(convert(273.15, Unit::Kelvin, Unit::Fahrenheit), 32.0);
+        assert_eq!(
+            convert(10.0, Unit::MetersPerSecond, Unit::KilometersPerHour),
+            36.0
+        );
+        assert_eq!(
+            convert(10.0, Unit::MetersPerSecond, Unit::MilesPerHour),
+            22.3694
+        );
+        assert_eq!(convert(101325.0, Unit::Pascal, Unit::Hectopascal), 1013.25);
+        assert_eq!(
+            convert(101325.0, Unit::Pascal, Unit::InchesOfMercury),
+            29.9213
+        );
+        assert_eq!(
+            convert(25.4, Unit::KilogramsPerSquareMeter, Unit::Inches),
+            1.0
+        );
+        assert_eq!(convert(0.5, Unit::Meters, Unit::Centimeters), 50.0);
+        assert_eq!(convert(3600.0, Unit::Seconds, Unit::Minutes), 60.0);
+    }
+
+    #[test]
+    fn temperature_differences_are_only_scaled() {
+        let difference = Quantity::TemperatureDifference;
+        let convert = |to| Unit::Kelvin.convert_quantity(2.0, to, difference).unwrap();
+        assert_eq!(convert(Unit::Kelvin), 2.0);
+        assert_eq!(convert(Unit::Celsius), 2.0);
+        assert_eq!(convert(Unit::Fahrenheit), 3.6);
+        assert_eq!(
+            Unit::Fahrenheit.convert_quantity(3.6, Unit::Celsius, difference),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn hourly_radiation_as_mean_irradiance() {
+        let (kj, w) = (Unit::KilojoulesPerSquareMeter, Unit::WattsPerSquareMeter);
+        // 1800 kJ/m2 during an hour are a mean of 500 W/m2
+        assert_eq!(
+            kj.convert_quantity(1800.0, w, Quantity::Irradiance),
+            Some(500.0)
+        );
+        assert_eq!(
+            w.convert_quantity(500.0, kj, Quantity::Irradiance),
+            Some(1800.0)
+        );
+        assert_eq!(
+            kj.convert_quantity(1800.0, kj, Quantity::Irradiance),
+            Some(1800.0)
+        );
+        // sums over other periods can't be converted
+        assert_eq!(kj.convert_quantity(1800.0, w, Quantity::Radiation), None);
+    }
+
+    #[test]
+    fn incompatible_units() {
+        assert_eq!(Unit::Kelvin.convert(1.0, Unit::Meters), None);
+        assert_eq!(
+            Unit::KilojoulesPerSquareMeter.convert(1.0, Unit::WattsPerSquareMeter),
+            None
+        );
+        assert_eq!(Unit::Percent.convert(1.0, Unit::Degrees), None);
+        assert_eq!(Unit::Percent.convert(1.0, Unit::Percent), Some(1.0));
+    }
+
+    #[test]
+    fn parse_dwd_labels() {
+        assert_eq!(Unit::parse("Grad C"), Some(Unit::Celsius));
+        assert_eq!(Unit::parse("°C"), Some(Unit::Celsius));
+        assert_eq!(Unit::parse(" km / h "), Some(Unit::KilometersPerHour));
+        assert_eq!(Unit::parse("W/m²"), Some(Unit::WattsPerSquareMeter));
+        assert_eq!(Unit::parse("CODE_TABLE"), Some(Unit::Code));
+        for unit in Unit::ALL {
+            assert_eq!(Unit::parse(unit.symbol()), Some(*unit));
+        }
+    }
+
+    #[test]
+    fn parse_unknown_labels() {
+        for label in ["", "furlong", "W", "kg", "°K", "m/s2"] {
+            assert_eq!(Unit::parse(label), None, "{label}");
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use self::{Intensity as I, PrecipitationType as P, WeatherCategory as C};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WeatherCodeQuery {
+    #[serde(default)]
+    pub decode_weather: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherCategory {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Overcast,
+    Fog,
+    Drizzle,
+    Rain,
+    Sleet,
+    Snow,
+    Showers,
+    Thunderstorm,
+    Dust,
+}
+
+impl WeatherCategory {
+    /// An emoji for short summaries (e.g. calendar events).
+    pub fn symbol(self) -> &'static str {
+        match self {
+            C::Clear => "☀",
+            C::PartlyCloudy => "🌤",
+            C::Cloudy => "⛅",
+            C::Overcast => "☁",
+            C::Fog => "🌫",
+            C::Drizzle | C::Showers => "🌦",
+            C::Rain => "🌧",
+            C::Sleet => "🌨",
+            C::Snow => "❄",
+            C::Thunderstorm => "⛈",
+            C::Dust => "🌪",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Intensity {
+    Light,
+    Moderate,
+    Heavy,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrecipitationType {
+    Drizzle,
+    FreezingDrizzle,
+    Rain,
+    FreezingRain,
+    Sleet,
+    Snow,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct WeatherCondition {
+    pub code: u8,
+    pub category: WeatherCategory,
+    pub intensity: Option<Intensity>,
+    pub precipitation: Option<PrecipitationType>,
+    pub icon_day: &'static str,
+    pub icon_night: &'static str,
+    pub description_en: &'static str,
+    pub description_de: &'static str,
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn condition(
+    code: u8,
+    category: WeatherCategory,
+    intensity: Option<Intensity>,
+    precipitation: Option<PrecipitationType>,
+    icon_day: &'static str,
+    icon_night: &'static str,
+    description_en: &'static str,
+    description_de: &'static str,
+) -> WeatherCondition {
+    WeatherCondition {
+        code,
+        category,
+        intensity,
+        precipitation,
+        icon_day,
+        icon_night,
+        description_en,
+        description_de,
+    }
+}
+
+/// Present weather (`ww`, WMO code table 4677) as used by MOSMIX and the POI reports.
+static PRESENT_WEATHER: phf::Map<u8, WeatherCondition> = phf::phf_map! {
+    0u8 => condition(0, C::Clear, None, None, "clear-day", "clear-night", "Clear sky", "Wolkenlos"),
+    1u8 => condition(1, C::PartlyCloudy, None, None, "partly-cloudy-day", "partly-cloudy-night", "Mainly clear", "Heiter"),
+    2u8 => condition(2, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Partly cloudy", "Bewölkt"),
+    3u8 => condition(3, C::Overcast, None, None, "overcast", "overcast", "Overcast", "Bedeckt"),
+    4u8 => condition(4, C::Dust, None, None, "smoke", "smoke", "Visibility reduced by smoke", "Sicht durch Rauch vermindert"),
+    5u8 => condition(5, C::Dust, None, None, "haze", "haze", "Haze", "Dunst"),
+    6u8 => condition(6, C::Dust, None, None, "dust", "dust", "Widespread dust in suspension", "Verbreitet Schwebstaub"),
+    7u8 => condition(7, C::Dust, None, None, "dust", "dust", "Dust or sand raised by wind", "Vom Wind aufgewirbelter Staub oder Sand"),
+    8u8 => condition(8, C::Dust, None, None, "dust", "dust", "Dust or sand whirls", "Staub- oder Sandwirbel"),
+    9u8 => condition(9, C::Dust, None, None, "dust", "dust", "Duststorm or sandstorm within sight", "Staub- oder Sandsturm in Sichtweite"),
+    10u8 => condition(10, C::Fog, None, None, "fog", "fog", "Mist", "Feuchter Dunst"),
+    11u8 => condition(11, C::Fog, None, None, "fog", "fog", "Patches of shallow fog", "Flacher Nebel in Schwaden"),
+    12u8 => condition(12, C::Fog, None, None, "fog", "fog", "Shallow fog", "Flacher Nebel"),
+    13u8 => condition(13, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Lightning visible, no thunder heard", "Wetterleuchten"),
+    14u8 => condition(14, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Precipitation within sight, not reaching the ground", "Niederschlag in Sicht, den Boden nicht erreichend"),
+    15u8 => condition(15, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Distant precipitation within sight", "Niederschlag in Sicht, in der Ferne"),
+    16u8 => condition(16, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Precipitation within sight, near the station", "Niederschlag in Sicht, in der Nähe"),
+    17u8 => condition(17, C::Thunderstorm, None, None, "thunderstorm", "thunderstorm", "Thunder without precipitation", "Gewitter ohne Niederschlag"),
+    18u8 => condition(18, C::Cloudy, None, None, "wind", "wind", "Squalls", "Böen"),
+    19u8 => condition(19, C::Thunderstorm, None, None, "tornado", "tornado", "Funnel clouds", "Trichterwolken"),
+    20u8 => condition(20, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Drizzle or snow grains in the past hour", "Sprühregen oder Schneegriesel in der letzten Stunde"),
+    21u8 => condition(21, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Rain in the past hour", "Regen in der letzten Stunde"),
+    22u8 => condition(22, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Snow in the past hour", "Schnee in der letzten Stunde"),
+    23u8 => condition(23, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Rain and snow or ice pellets in the past hour", "Schneeregen oder Eiskörner in der letzten Stunde"),
+    24u8 => condition(24, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Freezing drizzle or freezing rain in the past hour", "Gefrierender Sprühregen oder Regen in der letzten Stunde"),
+    25u8 => condition(25, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Rain showers in the past hour", "Regenschauer in der letzten Stunde"),
+    26u8 => condition(26, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Snow or rain and snow showers in the past hour", "Schnee- oder Schneeregenschauer in der letzten Stunde"),
+    27u8 => condition(27, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Hail showers in the past hour", "Hagelschauer in der letzten Stunde"),
+    28u8 => condition(28, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Fog in the past hour", "Nebel in der letzten Stunde"),
+    29u8 => condition(29, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Thunderstorm in the past hour", "Gewitter in der letzten Stunde"),
+    30u8 => condition(30, C::Dust, Some(I::Moderate), None, "dust", "dust", "Slight or moderate duststorm, decreasing", "Leichter oder mäßiger Staubsturm, abnehmend"),
+    31u8 => condition(31, C::Dust, Some(I::Moderate), None, "dust", "dust", "Slight or moderate duststorm", "Leichter oder mäßiger Staubsturm"),
+    32u8 => condition(32, C::Dust, Some(I::Moderate), None, "dust", "dust", "Slight or moderate duststorm, increasing", "Leichter oder mäßiger Staubsturm, zunehmend"),
+    33u8 => condition(33, C::Dust, Some(I::Heavy), None, "dust", "dust", "Severe duststorm, decreasing", "Schwerer Staubsturm, abnehmend"),
+    34u8 => condition(34, C::Dust, Some(I::Heavy), None, "dust", "dust", "Severe duststorm", "Schwerer Staubsturm"),
+    35u8 => condition(35, C::Dust, Some(I::Heavy), None, "dust", "dust", "Severe duststorm, increasing", "Schwerer Staubsturm, zunehmend"),
+    36u8 => condition(36, C::Snow, Some(I::Moderate), None, "snow", "snow", "Slight or moderate drifting snow", "Leichtes oder mäßiges Schneefegen"),
+    37u8 => condition(37, C::Snow, Some(I::Heavy), None, "heavy-snow", "heavy-snow", "Heavy drifting snow", "Starkes Schneefegen"),
+    38u8 => condition(38, C::Snow, Some(I::Moderate), None, "snow", "snow", "Slight or moderate blowing snow", "Leichtes oder mäßiges Schneetreiben"),
+    39u8 => condition(39, C::Snow, Some(I::Heavy), None, "heavy-snow", "heavy-snow", "Heavy blowing snow", "Starkes Schneetreiben"),
+    40u8 => condition(40, C::Fog, None, None, "fog", "fog", "Fog at a distance", "Nebel in der Ferne"),
+    41u8 => condition(41, C::Fog, None, None, "fog", "fog", "Fog in patches", "Nebelschwaden"),
+    42u8 => condition(42, C::Fog, None, None, "fog", "fog", "Fog, sky visible, thinning", "Nebel, Himmel erkennbar, dünner werdend"),
+    43u8 => condition(43, C::Fog, None, None, "fog", "fog", "Fog, sky invisible, thinning", "Nebel, Himmel nicht erkennbar, dünner werdend"),
+    44u8 => condition(44, C::Fog, None, None, "fog", "fog", "Fog, sky visible", "Nebel, Himmel erkennbar"),
+    45u8 => condition(45, C::Fog, None, None, "fog", "fog", "Fog", "Nebel"),
+    46u8 => condition(46, C::Fog, None, None, "fog", "fog", "Fog, sky visible, thickening", "Nebel, Himmel erkennbar, dichter werdend"),
+    47u8 => condition(47, C::Fog, None, None, "fog", "fog", "Fog, sky invisible, thickening", "Nebel, Himmel nicht erkennbar, dichter werdend"),
+    48u8 => condition(48, C::Fog, None, None, "fog", "fog", "Depositing rime fog, sky visible", "Nebel mit Reifansatz, Himmel erkennbar"),
+    49u8 => condition(49, C::Fog, None, None, "fog", "fog", "Depositing rime fog", "Nebel mit Reifansatz"),
+    50u8 => condition(50, C::Drizzle, Some(I::Light), Some(P::Drizzle), "drizzle", "drizzle", "Intermittent light drizzle", "Zeitweise leichter Sprühregen"),
+    51u8 => condition(51, C::Drizzle, Some(I::Light), Some(P::Drizzle), "drizzle", "drizzle", "Light drizzle", "Leichter Sprühregen"),
+    52u8 => condition(52, C::Drizzle, Some(I::Moderate), Some(P::Drizzle), "drizzle", "drizzle", "Intermittent moderate drizzle", "Zeitweise mäßiger Sprühregen"),
+    53u8 => condition(53, C::Drizzle, Some(I::Moderate), Some(P::Drizzle), "drizzle", "drizzle", "Moderate drizzle", "Mäßiger Sprühregen"),
+    54u8 => condition(54, C::Drizzle, Some(I::Heavy), Some(P::Drizzle), "drizzle", "drizzle", "Intermittent heavy drizzle", "Zeitweise starker Sprühregen"),
+    55u8 => condition(55, C::Drizzle, Some(I::Heavy), Some(P::Drizzle), "drizzle", "drizzle", "Heavy drizzle", "Starker Sprühregen"),
+    56u8 => condition(56, C::Drizzle, Some(I::Light), Some(P::FreezingDrizzle), "freezing-drizzle", "freezing-drizzle", "Light freezing drizzle", "Leichter gefrierender Sprühregen"),
+    57u8 => condition(57, C::Drizzle, Some(I::Heavy), Some(P::FreezingDrizzle), "freezing-drizzle", "freezing-drizzle", "Moderate or heavy freezing drizzle", "Mäßiger oder starker gefrierender Sprühregen"),
+    58u8 => condition(58, C::Rain, Some(I::Light), Some(P::Rain), "rain", "rain", "Light drizzle and rain", "Leichter Sprühregen mit Regen"),
+    59u8 => condition(59, C::Rain, Some(I::Heavy), Some(P::Rain), "rain", "rain", "Moderate or heavy drizzle and rain", "Mäßiger oder starker Sprühregen mit Regen"),
+    60u8 => condition(60, C::Rain, Some(I::Light), Some(P::Rain), "rain", "rain", "Intermittent light rain", "Zeitweise leichter Regen"),
+    61u8 => condition(61, C::Rain, Some(I::Light), Some(P::Rain), "rain", "rain", "Light rain", "Leichter Regen"),
+    62u8 => condition(62, C::Rain, Some(I::Moderate), Some(P::Rain), "rain", "rain", "Intermittent moderate rain", "Zeitweise mäßiger Regen"),
+    63u8 => condition(63, C::Rain, Some(I::Moderate), Some(P::Rain), "rain", "rain", "Moderate rain", "Mäßiger Regen"),
+    64u8 => condition(64, C::Rain, Some(I::Heavy), Some(P::Rain), "heavy-rain", "heavy-rain", "Intermittent heavy rain", "Zeitweise starker Regen"),
+    65u8 => condition(65, C::Rain, Some(I::Heavy), Some(P::Rain), "heavy-rain", "heavy-rain", "Heavy rain", "Starker Regen"),
+    66u8 => condition(66, C::Rain, Some(I::Light), Some(P::FreezingRain), "freezing-rain", "freezing-rain", "Light freezing rain", "Leichter gefrierender Regen"),
+    67u8 => condition(67, C::Rain, Some(I::Heavy), Some(P::FreezingRain), "freezing-rain", "freezing-rain", "Moderate or heavy freezing rain", "Mäßiger oder starker gefrierender Regen"),
+    68u8 => condition(68, C::Sleet, Some(I::Light), Some(P::Sleet), "sleet", "sleet", "Light rain and snow", "Leichter Schneeregen"),
+    69u8 => condition(69, C::Sleet, Some(I::Heavy), Some(P::Sleet), "sleet", "sleet", "Moderate or heavy rain and snow", "Mäßiger oder starker Schneeregen"),
+    70u8 => condition(70, C::Snow, Some(I::Light), Some(P::Snow), "snow", "snow", "Intermittent light snowfall", "Zeitweise leichter Schneefall"),
+    71u8 => condition(71, C::Snow, Some(I::Light), Some(P::Snow), "snow", "snow", "Light snowfall", "Leichter Schneefall"),
+    72u8 => condition(72, C::Snow, Some(I::Moderate), Some(P::Snow), "snow", "snow", "Intermittent moderate snowfall", "Zeitweise mäßiger Schneefall"),
+    73u8 => condition(73, C::Snow, Some(I::Moderate), Some(P::Snow), "snow", "snow", "Moderate snowfall", "Mäßiger Schneefall"),
+    74u8 => condition(74, C::Snow, Some(I::Heavy), Some(P::Snow), "heavy-snow", "heavy-snow", "Intermittent heavy snowfall", "Zeitweise starker Schneefall"),
+    75u8 => condition(75, C::Snow, Some(I::Heavy), Some(P::Snow), "heavy-snow", "heavy-snow", "Heavy snowfall", "Starker Schneefall"),
+    76u8 => condition(76, C::Snow, None, Some(P::Snow), "snow", "snow", "Diamond dust", "Eisnadeln"),
+    77u8 => condition(77, C::Snow, None, Some(P::Snow), "snow", "snow", "Snow grains", "Schneegriesel"),
+    78u8 => condition(78, C::Snow, Some(I::Light), Some(P::Snow), "snow", "snow", "Isolated star-like snow crystals", "Vereinzelte Schneesterne"),
+    79u8 => condition(79, C::Sleet, None, Some(P::Sleet), "sleet", "sleet", "Ice pellets", "Eiskörner"),
+    80u8 => condition(80, C::Showers, Some(I::Light), Some(P::Rain), "showers-day", "showers-night", "Light rain showers", "Leichter Regenschauer"),
+    81u8 => condition(81, C::Showers, Some(I::Moderate), Some(P::Rain), "showers-day", "showers-night", "Moderate or heavy rain showers", "Mäßiger oder starker Regenschauer"),
+    82u8 => condition(82, C::Showers, Some(I::Heavy), Some(P::Rain), "heavy-showers", "heavy-showers", "Violent rain showers", "Äußerst heftiger Regenschauer"),
+    83u8 => condition(83, C::Showers, Some(I::Light), Some(P::Sleet), "sleet-showers-day", "sleet-showers-night", "Light rain and snow showers", "Leichter Schneeregenschauer"),
+    84u8 => condition(84, C::Showers, Some(I::Heavy), Some(P::Sleet), "sleet-showers-day", "sleet-showers-night", "Moderate or heavy rain and snow showers", "Mäßiger oder starker Schneeregenschauer"),
+    85u8 => condition(85, C::Showers, Some(I::Light), Some(P::Snow), "snow-showers-day", "snow-showers-night", "Light snow showers", "Leichter Schneeschauer"),
+    86u8 => condition(86, C::Showers, Some(I::Heavy), Some(P::Snow), "snow-showers-day", "snow-showers-night", "Moderate or heavy snow showers", "Mäßiger oder starker Schneeschauer"),
+    87u8 => condition(87, C::Showers, Some(I::Light), Some(P::Snow), "snow-showers-day", "snow-showers-night", "Light snow pellet showers", "Leichter Graupelschauer"),
+    88u8 => condition(88, C::Showers, Some(I::Heavy), Some(P::Snow), "snow-showers-day", "snow-showers-night", "Moderate or heavy snow pellet showers", "Mäßiger oder starker Graupelschauer"),
+    89u8 => condition(89, C::Showers, Some(I::Light), None, "hail", "hail", "Light hail showers", "Leichter Hagelschauer"),
+    90u8 => condition(90, C::Showers, Some(I::Heavy), None, "hail", "hail", "Moderate or heavy hail showers", "Mäßiger oder starker Hagelschauer"),
+    91u8 => condition(91, C::Rain, Some(I::Light), Some(P::Rain), "rain", "rain", "Light rain after a thunderstorm", "Leichter Regen nach Gewitter"),
+    92u8 => condition(92, C::Rain, Some(I::Heavy), Some(P::Rain), "heavy-rain", "heavy-rain", "Moderate or heavy rain after a thunderstorm", "Mäßiger oder starker Regen nach Gewitter"),
+    93u8 => condition(93, C::Snow, Some(I::Light), Some(P::Snow), "snow", "snow", "Light snow or hail after a thunderstorm", "Leichter Schnee oder Hagel nach Gewitter"),
+    94u8 => condition(94, C::Snow, Some(I::Heavy), Some(P::Snow), "heavy-snow", "heavy-snow", "Moderate or heavy snow or hail after a thunderstorm", "Mäßiger oder starker Schnee oder Hagel nach Gewitter"),
+    95u8 => condition(95, C::Thunderstorm, Some(I::Moderate), None, "thunderstorm", "thunderstorm", "Slight or moderate thunderstorm", "Leichtes oder mäßiges Gewitter"),
+    96u8 => condition(96, C::Thunderstorm, Some(I::Moderate), None, "thunderstorm-hail", "thunderstorm-hail", "Thunderstorm with hail", "Gewitter mit Graupel oder Hagel"),
+    97u8 => condition(97, C::Thunderstorm, Some(I::Heavy), None, "thunderstorm", "thunderstorm", "Heavy thunderstorm", "Starkes Gewitter"),
+    98u8 => condition(98, C::Thunderstorm, Some(I::Heavy), None, "thunderstorm", "thunderstorm", "Thunderstorm with duststorm", "Gewitter mit Staubsturm"),
+    99u8 => condition(99, C::Thunderstorm, Some(I::Heavy), None, "thunderstorm-hail", "thunderstorm-hail", "Heavy thunderstorm with hail", "Starkes Gewitter mit Hagel"),
+};
+
+/// Past weather (`W1`/`W2`, WMO code table 4561).
+static PAST_WEATHER: phf::Map<u8, WeatherCondition> = phf::phf_map! {
+    0u8 => condition(0, C::Clear, None, None, "clear-day", "clear-night", "Cloud covering half of the sky or less", "Bewölkung höchstens die Hälfte des Himmels"),
+    1u8 => condition(1, C::Cloudy, None, None, "cloudy-day", "cloudy-night", "Variable cloud cover", "Wechselnde Bewölkung"),
+    2u8 => condition(2, C::Overcast, None, None, "overcast", "overcast", "Cloud covering more than half of the sky", "Bewölkung mehr als die Hälfte des Himmels"),
+    3u8 => condition(3, C::Dust, None, None, "dust", "dust", "Sandstorm, duststorm or blowing snow", "Sand-, Staubsturm oder Schneetreiben"),
+    4u8 => condition(4, C::Fog, None, None, "fog", "fog", "Fog or thick haze", "Nebel oder starker Dunst"),
+    5u8 => condition(5, C::Drizzle, None, Some(P::Drizzle), "drizzle", "drizzle", "Drizzle", "Sprühregen"),
+    6u8 => condition(6, C::Rain, None, Some(P::Rain), "rain", "rain", "Rain", "Regen"),
+    7u8 => condition(7, C::Snow, None, Some(P::Snow), "snow", "snow", "Snow or rain and snow", "Schnee oder Schneeregen"),
+    8u8 => condition(8, C::Showers, None, Some(P::Rain), "showers-day", "showers-night", "Showers", "Schauer"),
+    9u8 => condition(9, C::Thunderstorm, None, None, "thunderstorm", "thunderstorm", "Thunderstorm", "Gewitter"),
+};
+
+type Decoder = fn(f64) -> Option<&'static WeatherCondition>;
+
+/// Forecast elements containing present or past weather codes.
+static FORECAST_WEATHER_KEYS: &[(&str, Decoder)] = &[
+    ("significant_weather", decode_present_weather),
+    ("significant_weather_3h", decode_present_weather),
+    ("opt_significant_weather_1h", decode_present_weather),
+    ("opt_significant_weather_3h", decode_present_weather),
+    ("opt_significant_weather_6h", decode_present_weather),
+    ("opt_significant_weather_12h", decode_present_weather),
+    ("opt_significant_weather_24h", decode_present_weather),
+    ("past_weather_6h", decode_past_weather),
+];
+
+fn lookup(
+    table: &'static phf::Map<u8, WeatherCondition>,
+    code: f64,
+) -> Option<&'static WeatherCondition> {
+    if code.fract() != 0.0 || !(0.0..=u8::MAX as f64).contains(&code) {
+        return None;
+    }
+    table.get(&(code as u8))
+}
+
+pub fn decode_present_weather(code: f64) -> Option<&'static WeatherCondition> {
+    lookup(&PRESENT_WEATHER, code)
+}
+
+pub fn decode_past_weather(code: f64) -> Option<&'static WeatherCondition> {
+    lookup(&PAST_WEATHER, code)
+}
+
+pub type DecodedWeather = HashMap<&'static str, Vec<Option<&'static WeatherCondition>>>;
+
+pub fn decode_forecast_weather(data: &HashMap<&'static str, Vec<Value>>) -> DecodedWeather {
+    FORECAST_WEATHER_KEYS
+        .iter()
+        .filter_map(|(key, decode)| {
+            data.get(key).map(|values| {
+                (
+                    *key,
+                    values.iter().map(|v| v.as_f64().and_then(decode)).collect(),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_present_weather_code_decodes() {
+        for code in 0..=99u8 {
+            let condition = decode_present_weather(code as f64)
+                .unwrap_or_else(|| panic!("{code} doesn't decode"));
+            assert_eq!(condition.code, code);
+        }
+        assert!(decode_present_weather(100.0).is_none());
+        assert!(decode_present_weather(61.5).is_none());
+    }
+
+    #[test]
+    fn every_past_weather_code_decodes() {
+        for code in 0..=9u8 {
+            assert_eq!(decode_past_weather(code as f64).unwrap().code, code);
+        }
+        assert!(decode_past_weather(10.0).is_none());
+    }
+}
@@ -1,28 +1,203 @@
-use crate::{kml::deserialize_to_forecast, DwdError};
+use crate::{
+    diagnostics::{ParseOptions, ParseWarning},
+    elements::{
+        all_element_keys, dwd_name, element_by_key, resolve_key, KeyScheme, DERIVED_ELEMENTS,
+    },
+    geojson::{Point, ToFeature},
+    kml::deserialize_to_forecast,
+    time_format::{TimeFormatter, Timestamp},
+    units::{Unit, UnitSystem},
+    weather_code::{DecodedWeather, WeatherCondition},
+    DwdError,
+};
 use serde::Serialize;
-use std::{collections::HashMap, io::Cursor};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Cursor,
+};
+use utoipa::{
+    openapi::{
+        schema::{AdditionalProperties, SchemaType, Type},
+        ArrayBuilder, Object, ObjectBuilder, Ref,
+    },
+    ToSchema,
+};
 use zip::ZipArchive;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct Forecast {
     pub name: String,
     pub description: String,
     pub issuer: String,
     pub generating_process: String,
-    pub issue_time: u64,
+    pub issue_time: Timestamp,
     pub reference_models: Vec<ForecastReferenceModel>,
     pub coordinates: String,
-    pub data: HashMap<&'static str, Vec<serde_json::Value>>,
+    /// `None` if the KML coordinates were invalid (see `warnings`)
+    pub location: Option<Coordinates>,
+    #[schema(schema_with = data_schema)]
+    pub data: HashMap<&'static str, Vec<Value>>,
+    #[schema(schema_with = units_schema)]
+    pub units: BTreeMap<&'static str, Unit>,
     pub n_data_points: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<HashMap<String, Vec<Option<WeatherCondition>>>>)]
+    pub weather: Option<DecodedWeather>,
+    /// Elements that aren't known, keyed by their DWD name (only with passthrough)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<HashMap<String, Vec<Value>>>,
+    pub warnings: Vec<ParseWarning>,
 }
 
-#[derive(Serialize)]
+impl Forecast {
+    /// Returns the values of an element for every time step (`None` if missing).
+    pub fn column(&self, key: &str) -> Vec<Option<f64>> {
+        match self.data.get(key) {
+            Some(values) => values.iter().map(Value::as_f64).collect(),
+            None => vec![None; self.n_data_points],
+        }
+    }
+
+    pub fn convert_units(&mut self, system: UnitSystem) {
+        for (key, unit) in self.units.iter_mut() {
+            let Some(element) = element_by_key(key) else {
+                continue;
+            };
+            let Some(target) = system.target(element.quantity) else {
+                continue;
+            };
+            if unit
+                .convert_quantity(0.0, target, element.quantity)
+                .is_none()
+            {
+                continue;
+            }
+            if let Some(values) = self.data.get_mut(key) {
+                for value in values {
+                    if let Some(converted) = value
+                        .as_f64()
+                        .and_then(|v| unit.convert_quantity(v, target, element.quantity))
+                    {
+                        *value = Value::from(converted);
+                    }
+                }
+            }
+            *unit = target;
+        }
+    }
+
+    /// Only keeps the given elements (by key or DWD name), the time steps and derived elements.
+    /// Names that aren't known have to be in `raw`.
+    pub fn retain_elements(&mut self, names: &[String]) -> Result<(), DwdError> {
+        if names.is_empty() {
+            return Ok(());
+        }
+        let mut keys = HashSet::from(["time_steps"]);
+        keys.extend(DERIVED_ELEMENTS.keys());
+        let mut raw_names = HashSet::new();
+        for name in names {
+            match resolve_key(name) {
+                Some(key) => {
+                    keys.insert(key);
+                }
+                None if self.raw.as_ref().is_some_and(|raw| raw.contains_key(name)) => {
+                    raw_names.insert(name.as_str());
+                }
+                None => return Err(DwdError::UnknownElement(name.clone())),
+            }
+        }
+
+        self.data.retain(|key, _| keys.contains(key));
+        self.units.retain(|key, _| keys.contains(key));
+        if let Some(weather) = &mut self.weather {
+            weather.retain(|key, _| keys.contains(key));
+        }
+        if let Some(raw) = &mut self.raw {
+            raw.retain(|name, _| raw_names.contains(name.as_str()));
+        }
+        Ok(())
+    }
+
+    /// Has to be called after [`Forecast::convert_units`], which expects the keys of this API.
+    pub fn rename_keys(&mut self, scheme: KeyScheme) {
+        if scheme == KeyScheme::Friendly {
+            return;
+        }
+        let rename = |key: &'static str| dwd_name(key).unwrap_or(key);
+        self.data = self.data.drain().map(|(k, v)| (rename(k), v)).collect();
+        self.units = std::mem::take(&mut self.units)
+            .into_iter()
+            .map(|(k, v)| (rename(k), v))
+            .collect();
+        if let Some(weather) = &mut self.weather {
+            *weather = weather.drain().map(|(k, v)| (rename(k), v)).collect();
+        }
+    }
+
+    /// Has to be called last, [`Forecast::column`] won't return the time steps afterwards.
+    pub fn format_times(&mut self, formatter: TimeFormatter) {
+        self.issue_time.format_with(formatter);
+        for model in &mut self.reference_models {
+            model.reference_time.format_with(formatter);
+        }
+        if let Some(time_steps) = self.data.get_mut("time_steps") {
+            time_steps
+                .iter_mut()
+                .for_each(|t| formatter.format_value(t));
+        }
+    }
+}
+
+impl ToFeature for Forecast {
+    fn geometry(&self) -> Option<Point> {
+        self.location
+            .as_ref()
+            .map(|location| Point::new(location.longitude, location.latitude, location.elevation))
+    }
+}
+
+/// The values of each known element for every time step (`null` if missing) and the `time_steps`.
+fn data_schema() -> Object {
+    let values = ArrayBuilder::new()
+        .items(ObjectBuilder::new().schema_type(SchemaType::from_iter([Type::Number, Type::Null])))
+        .build();
+    all_element_keys()
+        .fold(
+            ObjectBuilder::new().property(
+                "time_steps",
+                ArrayBuilder::new().items(Ref::from_schema_name("Timestamp")),
+            ),
+            |object, key| object.property(key, values.clone()),
+        )
+        .additional_properties(Some(AdditionalProperties::FreeForm(false)))
+        .build()
+}
+
+/// The unit of each element in `data`.
+fn units_schema() -> Object {
+    all_element_keys()
+        .fold(ObjectBuilder::new(), |object, key| {
+            object.property(key, Ref::from_schema_name("Unit"))
+        })
+        .additional_properties(Some(AdditionalProperties::FreeForm(false)))
+        .build()
+}
+
+#[derive(Serialize, Debug, Clone, Copy, ToSchema)]
+pub struct Coordinates {
+    pub longitude: f64,
+    pub latitude: f64,
+    pub elevation: Option<f64>,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct ForecastReferenceModel {
     pub name: String,
-    pub reference_time: u64,
+    pub reference_time: Timestamp,
 }
 
-pub async fn get_forecast(station: &str) -> Result<Forecast, DwdError> {
+pub async fn get_forecast(station: &str, options: ParseOptions) -> Result<Forecast, DwdError> {
     let url = format!("https://opendata.dwd.de/weather/local_forecasts/mos/MOSMIX_L/single_stations/{station}/kml/MOSMIX_L_LATEST_{station}.kmz", station = station);
     let res = reqwest::get(&url)
         .await
@@ -40,7 +215,7 @@ pub async fn get_forecast(station: &str) -> Result<Forecast, DwdError> {
         let mut zip = ZipArchive::new(reader).map_err(|_| DwdError::BadZipFile)?;
         let file = zip.by_index(0).map_err(|_| DwdError::NoZipEntry)?;
 
-        deserialize_to_forecast(file)
+        deserialize_to_forecast(file, options)
     })
     .await
     .map_err(|_| DwdError::InternalError)?
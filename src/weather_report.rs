@@ -1,22 +1,280 @@
-use crate::DwdError;
-use chrono::{prelude::DateTime, NaiveDateTime, Utc};
+use crate::{
+    diagnostics::{Diagnostics, ParseMode, ParseWarning},
+    time_format::{TimeFormatter, Timestamp},
+    units::{Quantity, Unit, UnitSystem},
+    weather_code::{decode_past_weather, decode_present_weather, WeatherCondition},
+    DwdError,
+};
+use chrono::NaiveDateTime;
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 use serde_json::Value;
-use std::{collections::HashMap, io::Cursor, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Cursor,
+    str::FromStr,
+};
+use utoipa::{
+    openapi::{
+        schema::{AdditionalProperties, Type},
+        Object, ObjectBuilder, Ref,
+    },
+    ToSchema,
+};
 
 lazy_static! {
     static ref UNDEF_REGEX: Regex = Regex::new("^-*$").unwrap();
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Default, ToSchema)]
 pub struct WeatherReport {
-    units: HashMap<String, String>,
-    data: Vec<HashMap<String, Value>>,
+    #[schema(schema_with = units_schema)]
+    pub units: BTreeMap<ReportParameter, Unit>,
+    /// Units of columns that aren't known [`ReportParameter`]s and labels that aren't known [`Unit`]s
+    pub extra_units: BTreeMap<String, String>,
+    pub data: Vec<ReportRecord>,
+    pub warnings: Vec<ParseWarning>,
 }
 
-pub async fn get_weather_report(station: String) -> Result<WeatherReport, DwdError> {
+#[derive(Serialize, ToSchema)]
+pub struct ReportRecord {
+    pub timestamp: Timestamp,
+    #[serde(flatten)]
+    #[schema(schema_with = values_schema)]
+    pub values: BTreeMap<ReportParameter, f64>,
+    /// Unknown columns and values that couldn't be parsed as a number
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub extra: HashMap<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<WeatherCondition>)]
+    pub present_weather_condition: Option<&'static WeatherCondition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<WeatherCondition>)]
+    pub past_weather_1_condition: Option<&'static WeatherCondition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<WeatherCondition>)]
+    pub past_weather_2_condition: Option<&'static WeatherCondition>,
+}
+
+/// The keys of all parameters (every parameter has a CSV header).
+fn parameter_keys() -> BTreeSet<&'static str> {
+    REPORT_HEADER_TO_PARAMETER
+        .values()
+        .map(|param| param.key())
+        .collect()
+}
+
+fn units_schema() -> Object {
+    parameter_keys()
+        .into_iter()
+        .fold(ObjectBuilder::new(), |object, key| {
+            object.property(key, Ref::from_schema_name("Unit"))
+        })
+        .additional_properties(Some(AdditionalProperties::FreeForm(false)))
+        .build()
+}
+
+/// Open, the values are flattened next to `timestamp`, `extra` and the weather conditions.
+fn values_schema() -> Object {
+    parameter_keys()
+        .into_iter()
+        .fold(ObjectBuilder::new(), |object, key| {
+            object.property(key, ObjectBuilder::new().schema_type(Type::Number))
+        })
+        .additional_properties(Some(AdditionalProperties::FreeForm(true)))
+        .build()
+}
+
+impl WeatherReport {
+    pub fn convert_units(&mut self, system: UnitSystem) {
+        for (param, unit) in self.units.iter_mut() {
+            let quantity = param.quantity();
+            let Some(target) = system.target(quantity) else {
+                continue;
+            };
+            if unit.convert_quantity(0.0, target, quantity).is_none() {
+                continue;
+            }
+            for record in &mut self.data {
+                if let Some(value) = record.values.get_mut(param) {
+                    *value = unit
+                        .convert_quantity(*value, target, quantity)
+                        .unwrap_or(*value);
+                }
+            }
+            *unit = target;
+        }
+    }
+
+    pub fn format_times(&mut self, formatter: TimeFormatter) {
+        for record in &mut self.data {
+            record.timestamp.format_with(formatter);
+        }
+    }
+}
+
+impl ReportRecord {
+    pub fn decode_weather(&mut self) {
+        let code = |param| self.values.get(&param).copied();
+        self.present_weather_condition =
+            code(ReportParameter::PresentWeather).and_then(decode_present_weather);
+        self.past_weather_1_condition =
+            code(ReportParameter::PastWeather1).and_then(decode_past_weather);
+        self.past_weather_2_condition =
+            code(ReportParameter::PastWeather2).and_then(decode_past_weather);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReportParameter {
+    Temp,
+    DewPoint,
+    Temp5cm,
+    MaxTemp12h,
+    MinTemp12h,
+    MinTemp5cm12h,
+    MaxTempPreviousDay,
+    MinTempPreviousDay,
+    MinTemp5cmPreviousDay,
+    MeanTempPreviousDay,
+    WaterTemp,
+    RelativeHumidity,
+    WindSpeed,
+    WindDirection,
+    MaxWindGust1h,
+    MaxWindGust6h,
+    MaxWindSpeed1h,
+    MaxWindGustPreviousDay,
+    MaxWindSpeedPreviousDay,
+    Precipitation1h,
+    Precipitation3h,
+    Precipitation6h,
+    Precipitation12h,
+    Precipitation24h,
+    Evapotranspiration24h,
+    SnowDepth,
+    NewSnowDepth,
+    TotalCloudCover,
+    CloudBase,
+    Visibility,
+    SurfacePressure,
+    PresentWeather,
+    PastWeather1,
+    PastWeather2,
+    SunshineDuration1h,
+    SunshineDurationPreviousDay,
+    GlobalRadiation1h,
+    GlobalRadiation24h,
+    DiffuseRadiation1h,
+    DirectRadiation1h,
+    DirectRadiation24h,
+}
+
+impl ReportParameter {
+    pub fn key(self) -> &'static str {
+        match self {
+            ReportParameter::Temp => "temp",
+            ReportParameter::DewPoint => "dew_point",
+            ReportParameter::Temp5cm => "temp_5cm",
+            ReportParameter::MaxTemp12h => "max_temp_12h",
+            ReportParameter::MinTemp12h => "min_temp_12h",
+            ReportParameter::MinTemp5cm12h => "min_temp_5cm_12h",
+            ReportParameter::MaxTempPreviousDay => "max_temp_previous_day",
+            ReportParameter::MinTempPreviousDay => "min_temp_previous_day",
+            ReportParameter::MinTemp5cmPreviousDay => "min_temp_5cm_previous_day",
+            ReportParameter::MeanTempPreviousDay => "mean_temp_previous_day",
+            ReportParameter::WaterTemp => "water_temp",
+            ReportParameter::RelativeHumidity => "relative_humidity",
+            ReportParameter::WindSpeed => "wind_speed",
+            ReportParameter::WindDirection => "wind_direction",
+            ReportParameter::MaxWindGust1h => "max_wind_gust_1h",
+            ReportParameter::MaxWindGust6h => "max_wind_gust_6h",
+            ReportParameter::MaxWindSpeed1h => "max_wind_speed_1h",
+            ReportParameter::MaxWindGustPreviousDay => "max_wind_gust_previous_day",
+            ReportParameter::MaxWindSpeedPreviousDay => "max_wind_speed_previous_day",
+            ReportParameter::Precipitation1h => "precipitation_1h",
+            ReportParameter::Precipitation3h => "precipitation_3h",
+            ReportParameter::Precipitation6h => "precipitation_6h",
+            ReportParameter::Precipitation12h => "precipitation_12h",
+            ReportParameter::Precipitation24h => "precipitation_24h",
+            ReportParameter::Evapotranspiration24h => "evapotranspiration_24h",
+            ReportParameter::SnowDepth => "snow_depth",
+            ReportParameter::NewSnowDepth => "new_snow_depth",
+            ReportParameter::TotalCloudCover => "total_cloud_cover",
+            ReportParameter::CloudBase => "cloud_base",
+            ReportParameter::Visibility => "visibility",
+            ReportParameter::SurfacePressure => "surface_pressure",
+            ReportParameter::PresentWeather => "present_weather",
+            ReportParameter::PastWeather1 => "past_weather_1",
+            ReportParameter::PastWeather2 => "past_weather_2",
+            ReportParameter::SunshineDuration1h => "sunshine_duration_1h",
+            ReportParameter::SunshineDurationPreviousDay => "sunshine_duration_previous_day",
+            ReportParameter::GlobalRadiation1h => "global_radiation_1h",
+            ReportParameter::GlobalRadiation24h => "global_radiation_24h",
+            ReportParameter::DiffuseRadiation1h => "diffuse_radiation_1h",
+            ReportParameter::DirectRadiation1h => "direct_radiation_1h",
+            ReportParameter::DirectRadiation24h => "direct_radiation_24h",
+        }
+    }
+
+    pub fn quantity(self) -> Quantity {
+        use self::ReportParameter as P;
+        match self {
+            P::Temp
+            | P::DewPoint
+            | P::Temp5cm
+            | P::MaxTemp12h
+            | P::MinTemp12h
+            | P::MinTemp5cm12h
+            | P::MaxTempPreviousDay
+            | P::MinTempPreviousDay
+            | P::MinTemp5cmPreviousDay
+            | P::MeanTempPreviousDay
+            | P::WaterTemp => Quantity::Temperature,
+            P::RelativeHumidity | P::TotalCloudCover => Quantity::Percent,
+            P::WindSpeed
+            | P::MaxWindGust1h
+            | P::MaxWindGust6h
+            | P::MaxWindSpeed1h
+            | P::MaxWindGustPreviousDay
+            | P::MaxWindSpeedPreviousDay => Quantity::Speed,
+            P::WindDirection => Quantity::Direction,
+            P::Precipitation1h
+            | P::Precipitation3h
+            | P::Precipitation6h
+            | P::Precipitation12h
+            | P::Precipitation24h
+            | P::Evapotranspiration24h => Quantity::Precipitation,
+            P::SnowDepth | P::NewSnowDepth => Quantity::SnowDepth,
+            P::CloudBase => Quantity::Height,
+            P::Visibility => Quantity::Visibility,
+            P::SurfacePressure => Quantity::Pressure,
+            P::PresentWeather | P::PastWeather1 | P::PastWeather2 => Quantity::Code,
+            P::SunshineDuration1h | P::SunshineDurationPreviousDay => Quantity::Duration,
+            P::GlobalRadiation1h | P::DiffuseRadiation1h | P::DirectRadiation1h => {
+                Quantity::Irradiance
+            }
+            P::GlobalRadiation24h | P::DirectRadiation24h => Quantity::Radiation,
+        }
+    }
+
+    /// Looks up a column header of the POI CSV.
+    /// The headers contain stray spaces (`mean_wind_speed_during last_10 min_at_10_meters_above_ground`), so these are normalized first.
+    pub fn from_header(header: &str) -> Option<Self> {
+        let normalized = header
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("_")
+            .to_lowercase();
+        REPORT_HEADER_TO_PARAMETER.get(normalized.as_str()).copied()
+    }
+}
+
+pub async fn get_weather_report(
+    station: String,
+    mode: ParseMode,
+) -> Result<WeatherReport, DwdError> {
     let station = if station.len() < 5 {
         format!("{}{}", station, "_".repeat(5 - station.len()))
     } else {
@@ -36,81 +294,228 @@ pub async fn get_weather_report(station: String) -> Result<WeatherReport, DwdErr
         .map_err(|_| DwdError::NoReport)?;
     let reader = Cursor::new(res);
 
-    parse_weather_report(reader)
+    parse_weather_report(reader, mode)
 }
 
-pub fn parse_weather_report<R: std::io::Read>(report: R) -> Result<WeatherReport, DwdError> {
+enum Column {
+    Known(ReportParameter),
+    Extra(String),
+}
+
+pub fn parse_weather_report<R: std::io::Read>(
+    report: R,
+    mode: ParseMode,
+) -> Result<WeatherReport, DwdError> {
     let mut reader = csv::ReaderBuilder::new();
     let reader = reader.has_headers(false).delimiter(b';');
     let mut csv_file = reader.from_reader(report);
     let mut iter = csv_file.records();
 
-    let properties = iter
+    let columns: Vec<Column> = iter
         .next()
         .ok_or(DwdError::NoHeaderRow)?
+        .map_err(|_| DwdError::BadCsvLine)?
+        .iter()
+        .skip(2)
+        .map(|header| match ReportParameter::from_header(header) {
+            Some(param) => Column::Known(param),
+            None => Column::Extra(header.trim().to_owned()),
+        })
+        .collect();
+
+    let unit_row = iter
+        .next()
+        .ok_or(DwdError::NoUnitRow)?
         .map_err(|_| DwdError::BadCsvLine)?;
-    let properties: Vec<&str> = properties.iter().skip(2).collect();
-
-    let param_length = properties.len();
-
-    let units = {
-        let mut unit_map = HashMap::<String, String>::with_capacity(param_length);
-        for (unit_idx, unit) in iter
-            .next()
-            .ok_or(DwdError::NoUnitRow)?
-            .map_err(|_| DwdError::BadCsvLine)?
-            .iter()
-            .skip(2)
-            .enumerate()
-        {
-            unit_map.insert(properties[unit_idx].to_owned(), unit.to_owned());
-        }
-        unit_map
-    };
+    let unit_row: Vec<&str> = unit_row.iter().skip(2).collect();
 
     // skip german comments?!
     iter.next();
 
-    if units.len() != properties.len() {
+    if unit_row.len() != columns.len() {
         return Err(DwdError::UnitMismatch);
     }
 
-    let data: Vec<HashMap<String, Value>> = iter
-        .filter_map(|record| {
-            if record.is_err() {
-                return None;
+    let mut units = BTreeMap::new();
+    let mut extra_units = BTreeMap::new();
+    for (column, unit) in columns.iter().zip(unit_row) {
+        match (column, Unit::parse(unit)) {
+            (Column::Known(param), Some(unit)) => {
+                units.insert(*param, unit);
+            }
+            (Column::Known(param), None) => {
+                extra_units.insert(param.key().to_owned(), unit.to_owned());
+            }
+            (Column::Extra(name), _) => {
+                extra_units.insert(name.clone(), unit.to_owned());
+            }
+        }
+    }
+
+    let mut diagnostics = Diagnostics::new(mode);
+    let mut data = Vec::new();
+    for record in iter {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                diagnostics.warn(ParseWarning::SkippedRow {
+                    line: e.position().map(|p| p.line()).unwrap_or_default(),
+                    reason: e.to_string(),
+                })?;
+                continue;
             }
-            let record = record.unwrap();
-            let mut record = record.iter();
-            let date = record.next()?;
-            let time = record.next()?;
-
-            let timestamp = DateTime::<Utc>::from_utc(
-                NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%d.%m.%y %H:%M")
-                    .ok()?,
-                Utc,
-            )
-            .timestamp_millis();
-
-            let mut map = HashMap::<String, Value>::with_capacity(param_length + 1);
-            map.insert("timestamp".to_owned(), Value::from(timestamp));
-            for (idx, value) in record.enumerate() {
-                let prop = properties[idx].to_owned();
-                if !UNDEF_REGEX.is_match(value) {
-                    map.insert(
-                        prop,
-                        if let Ok(value) = f32::from_str(&value.replace(',', ".")) {
-                            Value::from(value)
-                        } else {
-                            Value::from(value)
-                        },
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let mut fields = record.iter();
+        let (date, time) = (fields.next().unwrap_or(""), fields.next().unwrap_or(""));
+
+        let Ok(timestamp) =
+            NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%d.%m.%y %H:%M")
+        else {
+            diagnostics.warn(ParseWarning::SkippedRow {
+                line,
+                reason: format!("invalid date \"{} {}\"", date, time),
+            })?;
+            continue;
+        };
+
+        let mut values = BTreeMap::new();
+        let mut extra = HashMap::new();
+        for (column, value) in columns.iter().zip(fields) {
+            if UNDEF_REGEX.is_match(value) {
+                continue;
+            }
+            let number = f64::from_str(&value.replace(',', "."));
+            match (column, number) {
+                (Column::Known(param), Ok(number)) => {
+                    values.insert(*param, number);
+                }
+                (Column::Known(param), Err(_)) => {
+                    extra.insert(param.key().to_owned(), Value::from(value));
+                }
+                (Column::Extra(name), number) => {
+                    extra.insert(
+                        name.clone(),
+                        number
+                            .map(Value::from)
+                            .unwrap_or_else(|_| Value::from(value)),
                     );
                 }
             }
+        }
 
-            Some(map)
-        })
-        .collect();
+        data.push(ReportRecord {
+            timestamp: Timestamp::from_millis(timestamp.and_utc().timestamp_millis()),
+            values,
+            extra,
+            present_weather_condition: None,
+            past_weather_1_condition: None,
+            past_weather_2_condition: None,
+        });
+    }
+
+    Ok(WeatherReport {
+        units,
+        extra_units,
+        data,
+        warnings: diagnostics.into_warnings(),
+    })
+}
+
+impl Serialize for ReportParameter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.key())
+    }
+}
 
-    Ok(WeatherReport { units, data })
+/// Maps the (normalized) column headers of the POI CSV to [`ReportParameter`]s.
+static REPORT_HEADER_TO_PARAMETER: phf::Map<&'static str, ReportParameter> = phf::phf_map! {
+    "dry_bulb_temperature_at_2_meter_above_ground" => ReportParameter::Temp,
+    "dew_point_temperature_at_2_meter_above_ground" => ReportParameter::DewPoint,
+    "temperature_at_5_cm_above_ground" => ReportParameter::Temp5cm,
+    "maximum_temperature_last_12_hours_2_meters_above_ground" => ReportParameter::MaxTemp12h,
+    "minimum_temperature_last_12_hours_2_meters_above_ground" => ReportParameter::MinTemp12h,
+    "minimum_temperature_last_12_hours_5_cm_above_ground" => ReportParameter::MinTemp5cm12h,
+    "maximum_of_temperature_for_previous_day" => ReportParameter::MaxTempPreviousDay,
+    "minimum_of_temperature_for_previous_day" => ReportParameter::MinTempPreviousDay,
+    "minimum_of_temperature_at_5_cm_above_ground_for_previous_day" => ReportParameter::MinTemp5cmPreviousDay,
+    "daily_mean_of_temperature_previous_day" => ReportParameter::MeanTempPreviousDay,
+    "sea/water_temperature" => ReportParameter::WaterTemp,
+    "relative_humidity" => ReportParameter::RelativeHumidity,
+    "mean_wind_speed_during_last_10_min_at_10_meters_above_ground" => ReportParameter::WindSpeed,
+    "mean_wind_direction_during_last_10_min_at_10_meters_above_ground" => ReportParameter::WindDirection,
+    "maximum_wind_speed_last_hour" => ReportParameter::MaxWindGust1h,
+    "maximum_wind_speed_during_last_6_hours" => ReportParameter::MaxWindGust6h,
+    "maximum_wind_speed_as_10_minutes_mean_during_last_hour" => ReportParameter::MaxWindSpeed1h,
+    "maximum_wind_speed_for_previous_day" => ReportParameter::MaxWindGustPreviousDay,
+    "maximum_of_10_minutes_mean_of_wind_speed_for_previous_day" => ReportParameter::MaxWindSpeedPreviousDay,
+    "precipitation_amount_last_hour" => ReportParameter::Precipitation1h,
+    "precipitation_amount_last_3_hours" => ReportParameter::Precipitation3h,
+    "precipitation_amount_last_6_hours" => ReportParameter::Precipitation6h,
+    "precipitation_last_12_hours" => ReportParameter::Precipitation12h,
+    "precipitation_amount_last_12_hours" => ReportParameter::Precipitation12h,
+    "precipitation_amount_last_24_hours" => ReportParameter::Precipitation24h,
+    "evaporation/evapotranspiration_last_24_hours" => ReportParameter::Evapotranspiration24h,
+    "total_snow_depth" => ReportParameter::SnowDepth,
+    "depth_of_new_snow" => ReportParameter::NewSnowDepth,
+    "cloud_cover_total" => ReportParameter::TotalCloudCover,
+    "height_of_base_of_lowest_cloud_above_station" => ReportParameter::CloudBase,
+    "horizontal_visibility" => ReportParameter::Visibility,
+    "pressure_reduced_to_mean_sea_level" => ReportParameter::SurfacePressure,
+    "present_weather" => ReportParameter::PresentWeather,
+    "past_weather_1" => ReportParameter::PastWeather1,
+    "past_weather_2" => ReportParameter::PastWeather2,
+    "total_time_of_sunshine_during_last_hour" => ReportParameter::SunshineDuration1h,
+    "total_time_of_sunshine_past_day" => ReportParameter::SunshineDurationPreviousDay,
+    "global_radiation_last_hour" => ReportParameter::GlobalRadiation1h,
+    "global_radiation_past_24_hours" => ReportParameter::GlobalRadiation24h,
+    "diffuse_solar_radiation_last_hour" => ReportParameter::DiffuseRadiation1h,
+    "direct_solar_radiation_last_hour" => ReportParameter::DirectRadiation1h,
+    "direct_solar_radiation_last_24_hours" => ReportParameter::DirectRadiation24h,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str =
+        "surface observations;;dry_bulb_temperature_at_2_meter_above_ground;present_weather
+Parameter description;;Grad C;CODE_TABLE
+Datum;Uhrzeit (UTC);Temperatur;Wetter
+24.10.26;21:00;12,3;61
+32.13.26;20:00;11,9;61
+24.10.26;19:00;11,5;---
+";
+
+    #[test]
+    fn lenient_parsing_skips_malformed_rows() {
+        let report = parse_weather_report(REPORT.as_bytes(), ParseMode::Lenient).unwrap();
+
+        assert_eq!(report.data.len(), 2);
+        assert_eq!(report.data[0].values[&ReportParameter::Temp], 12.3);
+        assert!(!report.data[1]
+            .values
+            .contains_key(&ReportParameter::PresentWeather));
+        assert_eq!(
+            report.warnings,
+            [ParseWarning::SkippedRow {
+                line: 5,
+                reason: "invalid date \"32.13.26 20:00\"".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn strict_parsing_rejects_malformed_rows() {
+        let result = parse_weather_report(REPORT.as_bytes(), ParseMode::Strict);
+
+        let Err(error @ DwdError::StrictParsing(ParseWarning::SkippedRow { line: 5, .. })) = result
+        else {
+            panic!("expected a strict parsing error");
+        };
+        assert_eq!(
+            actix_web::ResponseError::status_code(&error),
+            actix_web::http::StatusCode::BAD_GATEWAY
+        );
+    }
 }